@@ -0,0 +1,126 @@
+//! Package manager caches.
+//!
+//! Each manager is asked where it keeps downloaded packages: npm's cache,
+//! yarn's cache (under the global folder for yarn berry), pnpm's store and
+//! bun's install cache. Sizes come from [`crate::scan::dir_sizes`].
+
+use anyhow::{bail, Context, Result};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::agents::Agent;
+use crate::utils::find_in_path;
+
+/// Managers whose caches `--all-agents` covers, yarn standing for both
+/// classic and berry
+const AGENTS: [Agent; 4] = [Agent::Npm, Agent::Yarn, Agent::Pnpm, Agent::Bun];
+
+/// Package managers on PATH; yarn is told apart by its version
+pub fn installed_agents() -> Vec<Agent> {
+    AGENTS
+        .into_iter()
+        .filter(|agent| find_in_path(agent.name()).is_some())
+        .map(|agent| match agent {
+            Agent::Yarn => yarn_agent(),
+            agent => agent,
+        })
+        .collect()
+}
+
+/// Yarn berry or classic, going by `yarn --version`
+fn yarn_agent() -> Agent {
+    match output("yarn", &["--version"]) {
+        Ok(version) if is_berry(&version) => Agent::YarnBerry,
+        _ => Agent::Yarn,
+    }
+}
+
+fn is_berry(version: &str) -> bool {
+    version
+        .trim()
+        .split('.')
+        .next()
+        .and_then(|major| major.parse::<u32>().ok())
+        .is_some_and(|major| major >= 2)
+}
+
+/// Where `agent` keeps its cache, `None` when it cannot tell
+pub fn cache_dir(agent: Agent) -> Option<PathBuf> {
+    let (cmd, args) = agent.cache_dir_args();
+    parse_cache_dir(agent, &output(cmd, &args).ok()?)
+}
+
+fn parse_cache_dir(agent: Agent, output: &str) -> Option<PathBuf> {
+    // The path is the last line; bun may log before it
+    let dir = output.lines().map(str::trim).rfind(|l| !l.is_empty())?;
+    if dir == "undefined" {
+        return None;
+    }
+    let dir = PathBuf::from(dir);
+    Some(match agent {
+        Agent::YarnBerry => dir.join("cache"),
+        _ => dir,
+    })
+}
+
+/// Run `agent`'s cache clean command, failing with its error output
+pub fn clean(agent: Agent) -> Result<()> {
+    let (cmd, args) = agent.cache_clean_args();
+    let result = Command::new(cmd)
+        .args(&args)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run {}", cmd))?;
+    if !result.status.success() {
+        let stderr = String::from_utf8_lossy(&result.stderr);
+        match stderr.lines().map(str::trim).rfind(|l| !l.is_empty()) {
+            Some(line) => bail!("{} {} failed: {}", cmd, args.join(" "), line),
+            None => bail!("{} {} failed", cmd, args.join(" ")),
+        }
+    }
+    Ok(())
+}
+
+/// Standard output of a successful command
+fn output(cmd: &str, args: &[&str]) -> Result<String> {
+    let result = Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run {}", cmd))?;
+    if !result.status.success() {
+        bail!("{} {} failed", cmd, args.join(" "));
+    }
+    Ok(String::from_utf8_lossy(&result.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cache_dir() {
+        assert_eq!(
+            parse_cache_dir(Agent::Npm, "/home/me/.npm\n"),
+            Some(PathBuf::from("/home/me/.npm"))
+        );
+        assert_eq!(
+            parse_cache_dir(Agent::YarnBerry, "/home/me/.yarn/berry\n"),
+            Some(PathBuf::from("/home/me/.yarn/berry/cache"))
+        );
+        assert_eq!(
+            parse_cache_dir(
+                Agent::Bun,
+                "[0.12ms] config\n/home/me/.bun/install/cache\n\n"
+            ),
+            Some(PathBuf::from("/home/me/.bun/install/cache"))
+        );
+        assert_eq!(parse_cache_dir(Agent::Npm, "undefined\n"), None);
+        assert_eq!(parse_cache_dir(Agent::Pnpm, ""), None);
+
+        assert!(is_berry("4.1.0\n"));
+        assert!(!is_berry("1.22.19"));
+        assert!(!is_berry(""));
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::{bail, Result};
+use console::style;
+use inquire::Confirm;
+use std::process::Command;
+use tracing::{debug, info};
+
+use crate::agents::Agent;
+use crate::child;
+use crate::detect::package_manager_spec;
+use crate::display::StyledOutput;
+use crate::runner::DetectOptions;
+use crate::utils::{find_in_path, is_interactive};
+
+/// A command that can install a package manager binary
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstallStep {
+    pub program: &'static str,
+    pub args: Vec<String>,
+}
+
+impl InstallStep {
+    pub fn display(&self) -> String {
+        format!("{} {}", self.program, self.args.join(" "))
+    }
+}
+
+/// Ways to install `agent`, most preferred first. `version` pins the release
+/// (taken from the `packageManager` field).
+pub fn install_steps(agent: Agent, version: Option<&str>) -> Vec<InstallStep> {
+    let name = agent.name();
+    let mut steps = Vec::new();
+
+    // Corepack ships with Node.js and honours `packageManager` on its own
+    if matches!(
+        agent,
+        Agent::Yarn | Agent::YarnBerry | Agent::Pnpm | Agent::Pnpm6
+    ) {
+        steps.push(InstallStep {
+            program: "corepack",
+            args: vec!["enable".to_string(), name.to_string()],
+        });
+    }
+
+    // Yarn 2+ is not published to npm as `yarn`, so only corepack can get it
+    if !matches!(agent, Agent::Npm | Agent::YarnBerry) {
+        let package = match version {
+            Some(version) => format!("{}@{}", name, version),
+            None => name.to_string(),
+        };
+        steps.push(InstallStep {
+            program: "npm",
+            args: vec!["i".to_string(), "-g".to_string(), package],
+        });
+    }
+
+    steps
+}
+
+/// Make sure the binary for `agent` is on PATH.
+///
+/// When it is missing, kn installs it if `options.auto_install` is set or the
+/// user confirms, and otherwise fails with instructions on how to get it.
+pub fn ensure_agent(agent: Agent, options: &DetectOptions) -> Result<()> {
+    if find_in_path(agent.name()).is_some() {
+        return Ok(());
+    }
+
+    let version = package_manager_spec(&options.cwd)
+        .filter(|(name, _)| name == agent.name())
+        .and_then(|(_, version)| version);
+    debug!(
+        "{} not found on PATH (pinned version: {:?})",
+        agent.name(),
+        version
+    );
+
+    let steps: Vec<InstallStep> = install_steps(agent, version.as_deref())
+        .into_iter()
+        .filter(|step| find_in_path(step.program).is_some())
+        .collect();
+
+    if steps.is_empty() {
+        bail!(missing_message(agent, version.as_deref()));
+    }
+
+    if !options.auto_install && !confirm_install(agent, options) {
+        bail!(missing_message(agent, version.as_deref()));
+    }
+
+    for step in &steps {
+        StyledOutput::info(&format!(
+            "Installing {} with {}",
+            style(agent.name()).cyan(),
+            style(step.display()).dim(),
+        ));
+
+        let mut cmd = Command::new(step.program);
+        cmd.args(&step.args);
+        match child::run(&mut cmd) {
+            Ok(status) if status.success() && find_in_path(agent.name()).is_some() => {
+                info!("Installed {} via {}", agent.name(), step.program);
+                StyledOutput::success(&format!("{} is ready", agent.name()));
+                println!();
+                return Ok(());
+            }
+            Ok(_) | Err(_) => debug!("Install step failed: {}", step.display()),
+        }
+    }
+
+    bail!(
+        "Failed to install {}\n    {} {}",
+        agent.name(),
+        style("└").dim(),
+        style("Install it manually and make sure it is on your PATH").dim(),
+    )
+}
+
+fn confirm_install(agent: Agent, options: &DetectOptions) -> bool {
+    if options.programmatic || !is_interactive() {
+        return false;
+    }
+
+    Confirm::new(&format!(
+        "{} is not installed. Install it now?",
+        agent.name()
+    ))
+    .with_default(true)
+    .prompt()
+    .unwrap_or(false)
+}
+
+fn missing_message(agent: Agent, version: Option<&str>) -> String {
+    let hints: Vec<String> = if agent == Agent::Npm {
+        vec!["Install Node.js from https://nodejs.org".to_string()]
+    } else {
+        install_steps(agent, version)
+            .iter()
+            .map(|step| format!("Run {}", step.display()))
+            .collect()
+    };
+
+    let mut message = format!("{} is not installed", agent.name());
+    for (i, hint) in hints.iter().enumerate() {
+        let connector = if i == hints.len() - 1 { "└" } else { "├" };
+        message.push_str(&format!(
+            "\n    {} {}",
+            style(connector).dim(),
+            style(hint).dim()
+        ));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_install_steps() {
+        let steps = install_steps(Agent::Pnpm, Some("8.6.0"));
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].display(), "corepack enable pnpm");
+        assert_eq!(steps[1].display(), "npm i -g pnpm@8.6.0");
+
+        let steps = install_steps(Agent::Bun, None);
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].display(), "npm i -g bun");
+
+        let steps = install_steps(Agent::YarnBerry, Some("4.1.0"));
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].display(), "corepack enable yarn");
+
+        assert!(install_steps(Agent::Npm, None).is_empty());
+    }
+}
@@ -122,13 +122,27 @@ impl Agent {
         }
     }
 
-    /// Cache clean command and args
-    pub fn cache_clean_args(&self) -> Option<(&'static str, Vec<&'static str>)> {
+    /// Cache clean command and args. Yarn berry only cleans its global
+    /// mirror: a project's `.yarn/cache` may be committed (zero-installs).
+    pub fn cache_clean_args(&self) -> (&'static str, Vec<&'static str>) {
         match self {
-            Agent::Npm => Some(("npm", vec!["cache", "clean", "--force"])),
-            Agent::Yarn | Agent::YarnBerry => Some(("yarn", vec!["cache", "clean"])),
-            Agent::Pnpm | Agent::Pnpm6 => Some(("pnpm", vec!["store", "prune"])),
-            Agent::Bun => None, // Bun doesn't support cache clean yet
+            Agent::Npm => ("npm", vec!["cache", "clean", "--force"]),
+            Agent::Yarn => ("yarn", vec!["cache", "clean"]),
+            Agent::YarnBerry => ("yarn", vec!["cache", "clean", "--mirror"]),
+            Agent::Pnpm | Agent::Pnpm6 => ("pnpm", vec!["store", "prune"]),
+            Agent::Bun => ("bun", vec!["pm", "cache", "rm"]),
+        }
+    }
+
+    /// Command printing where the cache (or pnpm's store) lives. Yarn berry
+    /// prints its global folder, which holds the shared cache.
+    pub fn cache_dir_args(&self) -> (&'static str, Vec<&'static str>) {
+        match self {
+            Agent::Npm => ("npm", vec!["config", "get", "cache"]),
+            Agent::Yarn => ("yarn", vec!["cache", "dir"]),
+            Agent::YarnBerry => ("yarn", vec!["config", "get", "globalFolder"]),
+            Agent::Pnpm | Agent::Pnpm6 => ("pnpm", vec!["store", "path"]),
+            Agent::Bun => ("bun", vec!["pm", "cache"]),
         }
     }
 
@@ -0,0 +1,324 @@
+//! Build artifacts `kn clean` removes, and whether removing them is safe.
+//!
+//! Targets are globs relative to a package directory, such as `dist`,
+//! `**/.cache` or `*.tsbuildinfo`. They come from the `kn.clean` field of
+//! package.json, else the `clean_targets` config, else [`DEFAULT_TARGETS`].
+//!
+//! A candidate is a pure artifact when git tracks nothing in it and ignores
+//! it. Candidates holding tracked files are never removed, and ones git does
+//! not ignore may hold sources such as build scripts, so they need
+//! confirmation. Outside a git repository the `.gitignore` files are read
+//! directly and nothing counts as tracked. `node_modules` is always an
+//! artifact unless it holds tracked files.
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::config::get_clean_targets;
+use crate::glob::{relative_path, GlobSet};
+use crate::ignore::IgnoreFiles;
+use crate::utils::dir_size;
+
+/// What `kn clean` removes when nothing is configured
+pub const DEFAULT_TARGETS: [&str; 7] = [
+    "node_modules",
+    ".turbo",
+    ".next",
+    "dist",
+    "build",
+    ".vite",
+    ".nuxt",
+];
+
+/// Directories never searched for targets
+const SKIPPED_DIRS: [&str; 2] = [".git", "node_modules"];
+
+/// How safe removing a candidate is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Safety {
+    /// Ignored and untracked
+    Artifact,
+    /// Holds this many files git tracks; never removed
+    Tracked(usize),
+    /// Not ignored, so it may hold sources
+    NotIgnored,
+}
+
+/// An existing file or directory `kn clean` would remove
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub path: PathBuf,
+    /// Path relative to the package directory, `/`-separated
+    pub name: String,
+    pub is_dir: bool,
+    /// Size in bytes, of the contents for a directory
+    pub size: u64,
+    pub safety: Safety,
+}
+
+/// The `kn.clean` targets of the package.json at `manifest`
+pub fn package_targets(manifest: &Path) -> Result<Option<Vec<String>>> {
+    let contents = fs::read_to_string(manifest)
+        .with_context(|| format!("Failed to read {}", manifest.display()))?;
+    let json: Value = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse {}", manifest.display()))?;
+
+    match json["kn"].get("clean") {
+        Some(targets) => serde_json::from_value(targets.clone())
+            .map(Some)
+            .with_context(|| format!("Invalid kn.clean in {}", manifest.display())),
+        None => Ok(None),
+    }
+}
+
+/// The targets of packages without a `kn.clean` field
+pub fn default_targets() -> Vec<String> {
+    get_clean_targets().unwrap_or_else(|| DEFAULT_TARGETS.iter().map(|t| t.to_string()).collect())
+}
+
+/// What in `dir` matches `targets`, with sizes and safety. Matching
+/// directories are taken whole; `skip` lists directories not to search,
+/// such as nested workspace packages.
+pub fn find_candidates(dir: &Path, targets: &[String], skip: &[PathBuf]) -> Vec<Candidate> {
+    let globs = GlobSet::new(targets);
+    // Only patterns with a `/` can match below the top level
+    let deep = targets.iter().any(|target| target.contains('/'));
+    let mut matches = Vec::new();
+    collect_matches(dir, dir, &globs, deep, skip, &mut matches);
+    if matches.is_empty() {
+        return Vec::new();
+    }
+
+    let repo = in_git_repo(dir);
+    let mut ignores = IgnoreFiles::above(dir);
+    if !repo {
+        ignores.load(dir);
+    }
+
+    matches
+        .into_iter()
+        .map(|(path, name, is_dir)| {
+            let safety = if repo {
+                git_safety(dir, &name, is_dir)
+            } else if is_node_modules(&name) || ignores.is_ignored(&path, is_dir) {
+                Safety::Artifact
+            } else {
+                Safety::NotIgnored
+            };
+            let size = if is_dir {
+                dir_size(&path).unwrap_or(0)
+            } else {
+                fs::metadata(&path).map(|m| m.len()).unwrap_or(0)
+            };
+            Candidate {
+                path,
+                name,
+                is_dir,
+                size,
+                safety,
+            }
+        })
+        .collect()
+}
+
+fn collect_matches(
+    root: &Path,
+    dir: &Path,
+    globs: &GlobSet,
+    deep: bool,
+    skip: &[PathBuf],
+    matches: &mut Vec<(PathBuf, String, bool)>,
+) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        let path = entry.path();
+        let (Ok(file_type), Some(name)) = (entry.file_type(), relative_path(root, &path)) else {
+            continue;
+        };
+        // Links are left alone, and so is whatever they point to
+        if file_type.is_symlink() || skip.contains(&path) {
+            continue;
+        }
+        if globs.is_match(&name) {
+            matches.push((path, name, file_type.is_dir()));
+        } else if deep
+            && file_type.is_dir()
+            && !SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref())
+        {
+            collect_matches(root, &path, globs, deep, skip, matches);
+        }
+    }
+}
+
+fn is_node_modules(name: &str) -> bool {
+    name.rsplit('/').next() == Some("node_modules")
+}
+
+fn in_git_repo(dir: &Path) -> bool {
+    git(dir, &["rev-parse", "--is-inside-work-tree"])
+        .is_ok_and(|(success, stdout)| success && stdout.trim() == "true")
+}
+
+/// Safety of `name`, a path relative to `dir`
+fn git_safety(dir: &Path, name: &str, is_dir: bool) -> Safety {
+    // Tracked files first: an ignored directory can still hold some
+    let tracked = git(dir, &["ls-files", "-z", "--", name])
+        .map(|(_, stdout)| stdout.split('\0').filter(|f| !f.is_empty()).count())
+        .unwrap_or(0);
+    if tracked > 0 {
+        return Safety::Tracked(tracked);
+    }
+
+    let pattern = if is_dir {
+        format!("{}/", name)
+    } else {
+        name.to_string()
+    };
+    match git(dir, &["check-ignore", "-q", "--no-index", "--", &pattern]) {
+        Ok((true, _)) => Safety::Artifact,
+        _ if is_node_modules(name) => Safety::Artifact,
+        _ => Safety::NotIgnored,
+    }
+}
+
+/// Run git in `dir`, returning whether it succeeded and its output
+fn git(dir: &Path, args: &[&str]) -> Result<(bool, String)> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run git")?;
+    Ok((
+        output.status.success(),
+        String::from_utf8_lossy(&output.stdout).to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cleanup_test_dir(dir: &Path) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).expect("Failed to cleanup test directory");
+        }
+    }
+
+    fn safety_of(candidates: &[Candidate], name: &str) -> Option<Safety> {
+        candidates.iter().find(|c| c.name == name).map(|c| c.safety)
+    }
+
+    fn defaults() -> Vec<String> {
+        DEFAULT_TARGETS.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_git_safety() {
+        let test_dir = Path::new("/tmp/kn_test_artifacts_git");
+        cleanup_test_dir(test_dir);
+        for dir in ["dist", "build", ".next", "node_modules/pkg"] {
+            fs::create_dir_all(test_dir.join(dir)).unwrap();
+        }
+        fs::write(test_dir.join(".gitignore"), "dist/\n").unwrap();
+        fs::write(test_dir.join("dist/index.js"), "built").unwrap();
+        fs::write(test_dir.join("build/release.sh"), "echo").unwrap();
+        fs::write(test_dir.join(".next/cache"), "x").unwrap();
+        fs::write(test_dir.join("node_modules/pkg/index.js"), "x").unwrap();
+
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(args)
+                .current_dir(test_dir)
+                .output()
+                .unwrap()
+        };
+        if !git(&["init", "-q"]).status.success() {
+            // git is not installed
+            cleanup_test_dir(test_dir);
+            return;
+        }
+        git(&["add", ".gitignore", "build/release.sh"]);
+
+        let candidates = find_candidates(test_dir, &defaults(), &[]);
+        assert_eq!(candidates.len(), 4);
+        assert_eq!(safety_of(&candidates, "dist"), Some(Safety::Artifact));
+        assert_eq!(safety_of(&candidates, "build"), Some(Safety::Tracked(1)));
+        assert_eq!(safety_of(&candidates, ".next"), Some(Safety::NotIgnored));
+        assert_eq!(
+            safety_of(&candidates, "node_modules"),
+            Some(Safety::Artifact)
+        );
+        let dist = candidates.iter().find(|c| c.name == "dist").unwrap();
+        assert_eq!(dist.size, 5);
+
+        cleanup_test_dir(test_dir);
+    }
+
+    #[test]
+    fn test_glob_targets_without_git() {
+        let test_dir = Path::new("/tmp/kn_test_artifacts_plain");
+        cleanup_test_dir(test_dir);
+        for dir in [
+            "dist",
+            "build",
+            "src/.cache",
+            "packages/a/.cache",
+            "node_modules/.cache",
+        ] {
+            fs::create_dir_all(test_dir.join(dir)).unwrap();
+        }
+        fs::write(test_dir.join("tsconfig.tsbuildinfo"), "{}").unwrap();
+        fs::write(test_dir.join("src/app.tsbuildinfo"), "{}").unwrap();
+        fs::write(
+            test_dir.join(".gitignore"),
+            "/dist\n.cache/\n*.tsbuildinfo\n",
+        )
+        .unwrap();
+
+        let candidates = find_candidates(test_dir, &defaults(), &[]);
+        assert_eq!(safety_of(&candidates, "dist"), Some(Safety::Artifact));
+        assert_eq!(safety_of(&candidates, "build"), Some(Safety::NotIgnored));
+
+        let targets: Vec<String> = ["**/.cache", "*.tsbuildinfo"]
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        let candidates = find_candidates(test_dir, &targets, &[test_dir.join("packages/a")]);
+        let names: Vec<&str> = candidates.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["src/.cache", "tsconfig.tsbuildinfo"]);
+        assert!(candidates.iter().all(|c| c.safety == Safety::Artifact));
+        assert!(!candidates[1].is_dir);
+        assert_eq!(candidates[1].size, 2);
+
+        cleanup_test_dir(test_dir);
+    }
+
+    #[test]
+    fn test_package_targets() {
+        let test_dir = Path::new("/tmp/kn_test_artifacts_config");
+        cleanup_test_dir(test_dir);
+        fs::create_dir_all(test_dir).unwrap();
+        let manifest = test_dir.join("package.json");
+
+        fs::write(&manifest, r#"{"kn": {"clean": ["coverage", "**/.cache"]}}"#).unwrap();
+        assert_eq!(
+            package_targets(&manifest).unwrap(),
+            Some(vec!["coverage".to_string(), "**/.cache".to_string()])
+        );
+        fs::write(&manifest, r#"{"name": "a"}"#).unwrap();
+        assert_eq!(package_targets(&manifest).unwrap(), None);
+        fs::write(&manifest, r#"{"kn": {"clean": "dist"}}"#).unwrap();
+        assert!(package_targets(&manifest).is_err());
+
+        cleanup_test_dir(test_dir);
+    }
+}
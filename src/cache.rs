@@ -0,0 +1,395 @@
+//! Input-hashed script caching.
+//!
+//! Scripts listed in the `kn.cache` section of package.json are cached:
+//!
+//! ```json
+//! "kn": {
+//!   "cache": {
+//!     "build": { "inputs": ["src/**", "tsconfig.json"], "outputs": ["dist/**"], "env": ["NODE_ENV"] }
+//!   }
+//! }
+//! ```
+//!
+//! The hash covers the input files (every file of the package when no inputs
+//! are given), the script commands and arguments, the listed environment
+//! variables and the lockfile. A successful run stores its outputs and logs
+//! under the user's cache directory; a later run with the same hash replaces
+//! the outputs with the stored ones and replays the logs instead of running
+//! the script. Only single-script runs are cached, not `-p`, `-s` or
+//! `--recursive` ones.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::glob::GlobSet;
+
+/// Lockfiles that pin the dependencies a script runs with
+const LOCKFILES: [&str; 5] = [
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "package-lock.json",
+    "bun.lockb",
+    "bun.lock",
+];
+
+/// Directories never hashed or stored
+const SKIPPED_DIRS: [&str; 2] = ["node_modules", ".git"];
+
+/// Cache settings of one script
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct CacheConfig {
+    /// Globs of files the result depends on; empty means every file
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// Globs of files the script produces
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// Environment variables the result depends on
+    #[serde(default)]
+    pub env: Vec<String>,
+}
+
+/// A stored successful run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub hash: String,
+    /// Directory of the package the script belongs to
+    pub project: PathBuf,
+    pub script: String,
+    pub duration_ms: u64,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Output files, relative to the package directory
+    pub outputs: Vec<String>,
+}
+
+/// The cache settings of `script` in the package.json at `manifest`
+pub fn script_config(manifest: &Path, script: &str) -> Result<Option<CacheConfig>> {
+    let contents = fs::read_to_string(manifest)
+        .with_context(|| format!("Failed to read {}", manifest.display()))?;
+    let json: Value = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse {}", manifest.display()))?;
+
+    match json["kn"]["cache"].get(script) {
+        Some(config) => serde_json::from_value(config.clone())
+            .map(Some)
+            .with_context(|| format!("Invalid kn.cache.{} in {}", script, manifest.display())),
+        None => Ok(None),
+    }
+}
+
+/// Hash everything a cached run of `script` depends on. `commands` are the
+/// script's lifecycle commands and `env` the values of the configured
+/// variables.
+pub fn compute_hash(
+    package_dir: &Path,
+    script: &str,
+    commands: &[String],
+    args: &[String],
+    config: &CacheConfig,
+    env: &[(String, String)],
+) -> Result<String> {
+    let mut hasher = Sha256::new();
+    let mut field = |name: &str, value: &[u8]| {
+        hasher.update(name.as_bytes());
+        hasher.update((value.len() as u64).to_le_bytes());
+        hasher.update(value);
+    };
+
+    field("script", script.as_bytes());
+    for command in commands {
+        field("command", command.as_bytes());
+    }
+    for arg in args {
+        field("arg", arg.as_bytes());
+    }
+    for (key, value) in env {
+        field("env", format!("{}={}", key, value).as_bytes());
+    }
+
+    let inputs = if config.inputs.is_empty() {
+        vec!["**".to_string()]
+    } else {
+        config.inputs.clone()
+    };
+    for file in collect_files(package_dir, &inputs, &config.outputs) {
+        let contents = fs::read(package_dir.join(&file))
+            .with_context(|| format!("Failed to read input {}", file))?;
+        field("file", file.as_bytes());
+        field("contents", &Sha256::digest(&contents));
+    }
+
+    if let Some(lockfile) = find_lockfile(package_dir) {
+        let contents = fs::read(&lockfile)
+            .with_context(|| format!("Failed to read {}", lockfile.display()))?;
+        field("lockfile", &Sha256::digest(&contents));
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// The nearest lockfile at or above `dir`
+fn find_lockfile(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .flat_map(|ancestor| LOCKFILES.iter().map(move |name| ancestor.join(name)))
+        .find(|path| path.is_file())
+}
+
+/// Files below `dir` matching any of `include` and none of `exclude`, as
+/// sorted `/`-separated relative paths
+pub fn collect_files(dir: &Path, include: &[String], exclude: &[String]) -> Vec<String> {
+    let mut files = Vec::new();
+    walk_files(dir, "", &mut files);
+    let mut globs = GlobSet::new(include);
+    globs.ignore(exclude);
+    files.retain(|file| globs.is_match(file));
+    files.sort();
+    files
+}
+
+fn walk_files(dir: &Path, prefix: &str, files: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let relative = format!("{}{}", prefix, name);
+        match entry.file_type() {
+            Ok(t) if t.is_dir() && !SKIPPED_DIRS.contains(&name.as_str()) => {
+                walk_files(&entry.path(), &format!("{}/", relative), files)
+            }
+            Ok(t) if t.is_file() => files.push(relative),
+            _ => {}
+        }
+    }
+}
+
+/// The on-disk store of cached runs, one directory per hash
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// The cache of the current user, if a cache directory is available
+    pub fn open() -> Option<Self> {
+        dirs::cache_dir().map(|dir| Cache::at(dir.join("kn").join("scripts")))
+    }
+
+    pub fn at(dir: PathBuf) -> Self {
+        Cache { dir }
+    }
+
+    fn entry_dir(&self, hash: &str) -> PathBuf {
+        self.dir.join(hash)
+    }
+
+    pub fn lookup(&self, hash: &str) -> Option<CacheEntry> {
+        let contents = fs::read_to_string(self.entry_dir(hash).join("meta.json")).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// All entries, newest first
+    pub fn entries(&self) -> Vec<CacheEntry> {
+        let Ok(dirs) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut entries: Vec<CacheEntry> = dirs
+            .flatten()
+            .filter_map(|dir| self.lookup(&dir.file_name().to_string_lossy()))
+            .collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+        entries
+    }
+
+    /// Bytes used by an entry
+    pub fn size(&self, entry: &CacheEntry) -> u64 {
+        crate::utils::dir_size(&self.entry_dir(&entry.hash)).unwrap_or(0)
+    }
+
+    /// Store a successful run: the files matching `outputs` and its log
+    pub fn store(
+        &self,
+        mut entry: CacheEntry,
+        outputs: &[String],
+        log: &[u8],
+    ) -> Result<CacheEntry> {
+        entry.outputs = collect_files(&entry.project, outputs, &[]);
+
+        // Fill a temporary directory first so a half-written entry is never found
+        let tmp = self
+            .dir
+            .join(format!("{}.tmp-{}", entry.hash, std::process::id()));
+        let _ = fs::remove_dir_all(&tmp);
+        fs::create_dir_all(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?;
+        for file in &entry.outputs {
+            let target = tmp.join("outputs").join(file);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(entry.project.join(file), &target)
+                .with_context(|| format!("Failed to cache {}", file))?;
+        }
+        fs::write(tmp.join("output.log"), log)?;
+        fs::write(tmp.join("meta.json"), serde_json::to_string_pretty(&entry)?)?;
+
+        let target = self.entry_dir(&entry.hash);
+        let _ = fs::remove_dir_all(&target);
+        fs::rename(&tmp, &target)
+            .with_context(|| format!("Failed to write {}", target.display()))?;
+        Ok(entry)
+    }
+
+    /// Copy the outputs of `entry` back into its package and return its log.
+    /// Files matching `outputs` are removed first, so none are left over
+    /// from other runs.
+    pub fn restore(&self, entry: &CacheEntry, outputs: &[String]) -> Result<Vec<u8>> {
+        let dir = self.entry_dir(&entry.hash);
+        for file in collect_files(&entry.project, outputs, &[]) {
+            let path = entry.project.join(&file);
+            fs::remove_file(&path).with_context(|| format!("Failed to remove {}", file))?;
+        }
+        for file in &entry.outputs {
+            let target = entry.project.join(file);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(dir.join("outputs").join(file), &target)
+                .with_context(|| format!("Failed to restore {}", file))?;
+        }
+        Ok(fs::read(dir.join("output.log")).unwrap_or_default())
+    }
+
+    /// Delete an entry, returning the bytes freed
+    pub fn remove(&self, entry: &CacheEntry) -> Result<u64> {
+        let size = self.size(entry);
+        let dir = self.entry_dir(&entry.hash);
+        fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+        Ok(size)
+    }
+}
+
+/// Entries superseded by a newer entry of the same project and script.
+/// `entries` must be newest first, as returned by [`Cache::entries`].
+pub fn superseded(entries: &[CacheEntry]) -> Vec<&CacheEntry> {
+    let mut seen: Vec<(&Path, &str)> = Vec::new();
+    entries
+        .iter()
+        .filter(|entry| {
+            let key = (entry.project.as_path(), entry.script.as_str());
+            if seen.contains(&key) {
+                true
+            } else {
+                seen.push(key);
+                false
+            }
+        })
+        .collect()
+}
+
+/// Seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::glob::glob_match;
+
+    fn cleanup_test_dir(dir: &Path) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).expect("Failed to cleanup test directory");
+        }
+    }
+
+    fn write(path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("src/**", "src/a/b.ts"));
+        assert!(glob_match("src/**/*.ts", "src/index.ts"));
+        assert!(glob_match("src/*.ts", "src/index.ts"));
+        assert!(!glob_match("src/*.ts", "src/lib/index.ts"));
+        assert!(glob_match("dist", "dist/index.js"));
+        assert!(glob_match("./tsconfig.json", "tsconfig.json"));
+        assert!(glob_match("**", "anything/at/all"));
+        assert!(!glob_match("src/**", "test/a.ts"));
+    }
+
+    #[test]
+    fn test_hash_store_and_restore() {
+        let test_dir = Path::new("/tmp/kn_test_cache");
+        cleanup_test_dir(test_dir);
+        let package = test_dir.join("pkg");
+        write(&package.join("src/index.ts"), "one");
+        write(&package.join("node_modules/dep/index.js"), "ignored");
+        write(&package.join("dist/index.js"), "built");
+        write(&test_dir.join("package-lock.json"), "{}");
+
+        let config = CacheConfig {
+            inputs: Vec::new(),
+            outputs: vec!["dist/**".to_string()],
+            env: Vec::new(),
+        };
+        let commands = vec!["tsc".to_string()];
+        let hash = || compute_hash(&package, "build", &commands, &[], &config, &[]).unwrap();
+
+        let first = hash();
+        // Outputs and node_modules are not inputs
+        write(&package.join("dist/index.js"), "rebuilt");
+        write(&package.join("node_modules/dep/index.js"), "changed");
+        assert_eq!(hash(), first);
+        let with_arg =
+            compute_hash(&package, "build", &commands, &["-w".into()], &config, &[]).unwrap();
+        assert_ne!(with_arg, first);
+        write(&test_dir.join("package-lock.json"), "{\"v\":2}");
+        assert_ne!(hash(), first);
+        write(&package.join("src/index.ts"), "two");
+        let second = hash();
+
+        let cache = Cache::at(test_dir.join("cache"));
+        assert!(cache.lookup(&second).is_none());
+        let entry = CacheEntry {
+            hash: second.clone(),
+            project: package.clone(),
+            script: "build".to_string(),
+            duration_ms: 1500,
+            timestamp: now(),
+            outputs: Vec::new(),
+        };
+        let stored = cache
+            .store(entry, &config.outputs, b"compiled\n")
+            .expect("Failed to store");
+        assert_eq!(stored.outputs, ["dist/index.js"]);
+
+        // Stale outputs go, whatever else is in the directory stays
+        fs::remove_file(package.join("dist/index.js")).unwrap();
+        write(&package.join("dist/old.js"), "stale");
+        write(&package.join("dist/.keep"), "");
+        let found = cache.lookup(&second).expect("Entry should be cached");
+        let outputs = vec!["dist/*.js".to_string()];
+        assert_eq!(cache.restore(&found, &outputs).unwrap(), b"compiled\n");
+        assert_eq!(
+            fs::read_to_string(package.join("dist/index.js")).unwrap(),
+            "rebuilt"
+        );
+        assert!(!package.join("dist/old.js").exists());
+        assert!(package.join("dist/.keep").exists());
+        assert_eq!(cache.entries().len(), 1);
+        assert!(cache.remove(&found).unwrap() > 0);
+        assert!(cache.entries().is_empty());
+
+        cleanup_test_dir(test_dir);
+    }
+}
@@ -0,0 +1,408 @@
+//! Child process management.
+//!
+//! On Unix every child is started in its own process group. kn forwards
+//! SIGINT/SIGTERM/SIGHUP to that group, hands it the terminal while a
+//! foreground child runs, and escalates to SIGKILL when the group does not
+//! exit within a grace period. This keeps dev servers and their grandchildren
+//! from outliving kn when it is interrupted or stopped by a container runtime.
+
+use std::io;
+use std::process::{ChildStderr, ChildStdout, Command, ExitStatus};
+use std::time::Duration;
+
+/// How long a signalled process group may take to exit before it is killed
+pub const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// A spawned child that receives forwarded signals until it is waited on
+pub struct Child {
+    inner: std::process::Child,
+    #[cfg(unix)]
+    terminal: Option<unix::TerminalGuard>,
+}
+
+impl Child {
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+
+    pub fn take_stdout(&mut self) -> Option<ChildStdout> {
+        self.inner.stdout.take()
+    }
+
+    pub fn take_stderr(&mut self) -> Option<ChildStderr> {
+        self.inner.stderr.take()
+    }
+
+    /// Wait for the child, then give stragglers of a signalled group the
+    /// rest of the grace period before killing them
+    pub fn wait(mut self) -> io::Result<ExitStatus> {
+        let status = self.inner.wait();
+        #[cfg(unix)]
+        {
+            unix::finish(self.inner.id() as i32);
+            drop(self.terminal.take());
+        }
+        status
+    }
+}
+
+/// Spawn `cmd` in the background (its output is usually piped)
+pub fn spawn(cmd: &mut Command) -> io::Result<Child> {
+    #[cfg(unix)]
+    {
+        unix::spawn(cmd, false)
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(Child {
+            inner: cmd.spawn()?,
+        })
+    }
+}
+
+/// Spawn `cmd` as a foreground child: it gets the terminal until it is
+/// waited on, so it can read from it even when its output is piped
+pub fn spawn_foreground(cmd: &mut Command) -> io::Result<Child> {
+    #[cfg(unix)]
+    {
+        unix::spawn(cmd, true)
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(Child {
+            inner: cmd.spawn()?,
+        })
+    }
+}
+
+/// Spawn `cmd` as a foreground child and wait for it to finish
+pub fn run(cmd: &mut Command) -> io::Result<ExitStatus> {
+    #[cfg(unix)]
+    {
+        spawn_foreground(cmd)?.wait()
+    }
+    #[cfg(not(unix))]
+    {
+        cmd.status()
+    }
+}
+
+/// Ask the child `pid` (and its process group) to stop, killing it if it is
+/// still running after the grace period
+pub fn terminate(pid: u32) {
+    #[cfg(unix)]
+    {
+        unix::signal_group(pid as i32, libc::SIGTERM);
+    }
+    #[cfg(windows)]
+    {
+        let _ = Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status();
+    }
+}
+
+/// Keep kn alive when it receives SIGINT/SIGTERM/SIGHUP, for loops like
+/// `kn watch` that stop themselves once [`interrupted`] reports a signal.
+/// Running children still receive the signal.
+pub fn catch_interrupts() -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        unix::catch_interrupts()
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(())
+    }
+}
+
+/// The stop signal kn received since [`catch_interrupts`], if any
+pub fn interrupted() -> Option<i32> {
+    #[cfg(unix)]
+    {
+        match unix::INTERRUPTED.load(std::sync::atomic::Ordering::SeqCst) {
+            0 => None,
+            signal => Some(signal),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Map an exit status to a shell-style exit code (`128 + n` for signal deaths)
+pub fn exit_code(status: &ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::io;
+    use std::os::unix::process::CommandExt;
+    use std::process::Command;
+    use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+    use std::sync::{Mutex, MutexGuard, OnceLock};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    use signal_hook::iterator::Signals;
+    use tracing::debug;
+
+    use super::{Child, GRACE_PERIOD};
+
+    const FORWARDED_SIGNALS: [i32; 3] = [SIGINT, SIGTERM, SIGHUP];
+
+    /// A running child process group and the last signal sent to it
+    struct Group {
+        pgid: i32,
+        signalled: Option<i32>,
+    }
+
+    /// Process groups of all running children
+    static GROUPS: Mutex<Vec<Group>> = Mutex::new(Vec::new());
+    static FORWARDER: OnceLock<io::Result<()>> = OnceLock::new();
+    static CATCHING: AtomicBool = AtomicBool::new(false);
+    /// The last signal received while catching, 0 for none
+    pub static INTERRUPTED: AtomicI32 = AtomicI32::new(0);
+
+    pub fn spawn(cmd: &mut Command, foreground: bool) -> io::Result<Child> {
+        install_forwarder()?;
+
+        let interactive = foreground && owns_terminal();
+        cmd.process_group(0);
+        if interactive {
+            // SAFETY: only async-signal-safe libc calls run between fork and exec.
+            unsafe {
+                cmd.pre_exec(|| {
+                    // Claim the terminal from the child side too, so it never
+                    // reads from the tty before the parent has handed it over.
+                    libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+                    libc::tcsetpgrp(libc::STDIN_FILENO, libc::getpgrp());
+                    libc::signal(libc::SIGTTOU, libc::SIG_DFL);
+                    Ok(())
+                });
+            }
+        }
+
+        let inner = cmd.spawn()?;
+        let pgid = inner.id() as i32;
+        let terminal = interactive.then(|| TerminalGuard::hand_to(pgid));
+        groups().push(Group {
+            pgid,
+            signalled: None,
+        });
+
+        Ok(Child { inner, terminal })
+    }
+
+    /// Unregister a group whose leader exited, reaping signalled stragglers
+    pub fn finish(pgid: i32) {
+        let signalled = {
+            let mut groups = groups();
+            match groups.iter().position(|g| g.pgid == pgid) {
+                Some(index) => groups.remove(index).signalled,
+                None => None,
+            }
+        };
+
+        if signalled.is_some() {
+            reap_group(pgid, Instant::now() + GRACE_PERIOD);
+        }
+    }
+
+    fn groups() -> MutexGuard<'static, Vec<Group>> {
+        GROUPS.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Install the process-wide signal forwarder (once)
+    fn install_forwarder() -> io::Result<()> {
+        let installed = FORWARDER.get_or_init(|| {
+            let mut signals = Signals::new(FORWARDED_SIGNALS)?;
+            thread::Builder::new()
+                .name("kn-signals".to_string())
+                .spawn(move || {
+                    for signal in signals.forever() {
+                        forward(signal);
+                    }
+                })?;
+            Ok(())
+        });
+
+        match installed {
+            Ok(()) => Ok(()),
+            Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+        }
+    }
+
+    pub fn catch_interrupts() -> io::Result<()> {
+        install_forwarder()?;
+        CATCHING.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn forward(signal: i32) {
+        let catching = CATCHING.load(Ordering::SeqCst);
+        if catching {
+            INTERRUPTED.store(signal, Ordering::SeqCst);
+        }
+
+        let pgids: Vec<i32> = groups().iter().map(|g| g.pgid).collect();
+        if pgids.is_empty() && !catching {
+            // No child running: behave as if kn never installed a handler.
+            let _ = signal_hook::low_level::emulate_default_handler(signal);
+            return;
+        }
+
+        for pgid in pgids {
+            signal_group(pgid, signal);
+        }
+    }
+
+    /// Send `signal` to a registered group and schedule a SIGKILL in case it
+    /// ignores it
+    pub fn signal_group(pgid: i32, signal: i32) {
+        let first = {
+            let mut groups = groups();
+            match groups.iter_mut().find(|g| g.pgid == pgid) {
+                Some(group) => group.signalled.replace(signal).is_none(),
+                None => return,
+            }
+        };
+
+        debug!("Sending signal {} to process group {}", signal, pgid);
+        unsafe {
+            libc::kill(-pgid, signal);
+        }
+
+        if first {
+            thread::spawn(move || {
+                thread::sleep(GRACE_PERIOD);
+                if groups().iter().any(|g| g.pgid == pgid) {
+                    debug!("Process group {} did not exit, sending SIGKILL", pgid);
+                    unsafe {
+                        libc::kill(-pgid, libc::SIGKILL);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Wait for stragglers of a signalled group, killing them after `deadline`
+    fn reap_group(pgid: i32, deadline: Instant) {
+        while group_alive(pgid) {
+            if Instant::now() >= deadline {
+                debug!("Killing remaining members of process group {}", pgid);
+                unsafe {
+                    libc::kill(-pgid, libc::SIGKILL);
+                }
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    fn group_alive(pgid: i32) -> bool {
+        unsafe { libc::kill(-pgid, 0) == 0 }
+    }
+
+    /// Whether kn is the foreground process group of an interactive terminal
+    fn owns_terminal() -> bool {
+        unsafe {
+            libc::isatty(libc::STDIN_FILENO) == 1
+                && libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp()
+        }
+    }
+
+    /// Gives the terminal to a child group and takes it back on drop
+    pub struct TerminalGuard;
+
+    impl TerminalGuard {
+        fn hand_to(pgid: i32) -> Self {
+            set_foreground(pgid);
+            TerminalGuard
+        }
+    }
+
+    impl Drop for TerminalGuard {
+        fn drop(&mut self) {
+            set_foreground(unsafe { libc::getpgrp() });
+        }
+    }
+
+    fn set_foreground(pgid: i32) {
+        unsafe {
+            // A background group calling tcsetpgrp receives SIGTTOU unless ignored
+            let previous = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+            libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+            libc::signal(libc::SIGTTOU, previous);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::process::Stdio;
+
+    fn sh(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+
+    #[test]
+    fn test_exit_code_passthrough() {
+        let status = run(&mut sh("exit 3")).expect("Failed to run sh");
+        assert_eq!(exit_code(&status), 3);
+
+        let status = run(&mut sh("true")).expect("Failed to run sh");
+        assert_eq!(exit_code(&status), 0);
+    }
+
+    #[test]
+    fn test_exit_code_signal_death() {
+        let status = run(&mut sh("kill -TERM $$")).expect("Failed to run sh");
+        assert_eq!(exit_code(&status), 143);
+
+        let status = run(&mut sh("kill -KILL $$")).expect("Failed to run sh");
+        assert_eq!(exit_code(&status), 137);
+    }
+
+    #[test]
+    fn test_child_runs_in_own_process_group() {
+        // `ps` may be unavailable in minimal sandboxes
+        if Command::new("ps").arg("-o").arg("pgid=").output().is_err() {
+            return;
+        }
+
+        let status = run(&mut sh(r#"[ "$(ps -o pgid= -p $$ | tr -d ' ')" = "$$" ]"#))
+            .expect("Failed to run sh");
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_terminate_background_child() {
+        let mut child =
+            spawn(sh("echo started; sleep 30").stdout(Stdio::piped())).expect("Failed to spawn sh");
+
+        let mut stdout = child.take_stdout().unwrap();
+        let mut started = [0u8; 8];
+        stdout.read_exact(&mut started).unwrap();
+
+        terminate(child.id());
+        let status = child.wait().expect("Failed to wait for sh");
+        assert_eq!(exit_code(&status), 143);
+    }
+}
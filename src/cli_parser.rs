@@ -1,41 +1,218 @@
 use console::style;
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::completions::{Shell, SHELLS};
+use crate::dotenv::EnvOptions;
+use crate::watcher::WatchRule;
 
 use crate::utils::levenshtein_distance;
 
+/// A top-level command as the help, suggestions and shell completions see it
+pub struct CommandSpec {
+    /// The name followed by its aliases
+    pub names: &'static [&'static str],
+    pub flags: &'static [&'static str],
+    /// What the first positional argument is
+    pub first: Values,
+    /// What later positional arguments are
+    pub rest: Values,
+}
+
+/// Kinds of values shell completion offers for an argument
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Values {
+    None,
+    Files,
+    Directories,
+    /// Script names of the nearest package.json
+    Scripts,
+    /// Dependency names of the nearest package.json
+    Dependencies,
+    /// Package names of the workspace
+    Workspaces,
+    Words(&'static [&'static str]),
+}
+
+const RUN_FLAGS: &[&str] = &[
+    "--last",
+    "--if-present",
+    "-p",
+    "--parallel",
+    "-s",
+    "--sequential",
+    "--max-parallel",
+    "--continue-on-error",
+    "-r",
+    "--recursive",
+    "--root",
+    "--force",
+    "--log",
+    "--filter",
+    "--since",
+    "--env-file",
+    "--mode",
+    "--no-env",
+];
+
+/// Every command kn accepts
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec {
+        names: &["install", "i", "add"],
+        flags: &[
+            "-D",
+            "--save-dev",
+            "-g",
+            "--global",
+            "-E",
+            "--save-exact",
+            "--ignore-scripts",
+        ],
+        first: Values::None,
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["run", "r"],
+        flags: RUN_FLAGS,
+        first: Values::Scripts,
+        rest: Values::Scripts,
+    },
+    CommandSpec {
+        names: &["uninstall", "remove", "rm"],
+        flags: &["-g", "--global"],
+        first: Values::Dependencies,
+        rest: Values::Dependencies,
+    },
+    CommandSpec {
+        names: &["execute", "exec", "x"],
+        flags: &["--env-file", "--mode", "--no-env"],
+        first: Values::None,
+        rest: Values::Files,
+    },
+    CommandSpec {
+        names: &["upgrade", "update", "up"],
+        flags: &["-i", "--interactive", "--latest"],
+        first: Values::Dependencies,
+        rest: Values::Dependencies,
+    },
+    CommandSpec {
+        names: &["upgrade-self"],
+        flags: &[],
+        first: Values::None,
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["clean-install", "ci"],
+        flags: &["--force", "--no-optional"],
+        first: Values::None,
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["list", "ls"],
+        flags: &["--json"],
+        first: Values::None,
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["info"],
+        flags: &["-v", "--verbose"],
+        first: Values::None,
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["env"],
+        flags: &["-a", "--all", "--env-file", "--mode"],
+        first: Values::None,
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["watch", "w"],
+        flags: &[
+            "--restart",
+            "--queue",
+            "--ignore",
+            "--poll",
+            "--debounce",
+            "--on",
+        ],
+        first: Values::Scripts,
+        rest: Values::Files,
+    },
+    CommandSpec {
+        names: &["clean"],
+        flags: &[
+            "--cache",
+            "--all",
+            "-g",
+            "--global",
+            "--dry-run",
+            "-y",
+            "--yes",
+            "-r",
+            "--recursive",
+            "--scan",
+            "--older-than",
+            "--all-agents",
+        ],
+        first: Values::None,
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["view"],
+        flags: &[],
+        first: Values::None,
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["history"],
+        flags: &["-a", "--all", "-n", "--limit", "--json"],
+        first: Values::None,
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["cache"],
+        flags: &["-a", "--all"],
+        first: Values::Words(&["list", "prune", "clear"]),
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["completions"],
+        flags: &[],
+        first: Values::Words(SHELLS),
+        rest: Values::None,
+    },
+    CommandSpec {
+        names: &["help"],
+        flags: &[],
+        first: Values::None,
+        rest: Values::None,
+    },
+];
+
+/// Flags that take a value, and what that value is
+pub const VALUE_FLAGS: &[(&str, Values)] = &[
+    ("-C", Values::Directories),
+    ("--filter", Values::Workspaces),
+    ("--ignore", Values::None),
+    ("--debounce", Values::None),
+    ("--on", Values::None),
+    ("--log", Values::Files),
+    ("--env-file", Values::Files),
+    ("--mode", Values::None),
+    ("--max-parallel", Values::None),
+    ("--since", Values::None),
+    ("-n", Values::None),
+    ("--limit", Values::None),
+    ("--scan", Values::Directories),
+    ("--older-than", Values::None),
+];
+
 // Find similar commands based on user input
 fn find_similar_commands(input: &str) -> Vec<(String, usize)> {
-    let all_commands = [
-        "install",
-        "i",
-        "add",
-        "run",
-        "r",
-        "uninstall",
-        "remove",
-        "rm",
-        "execute",
-        "exec",
-        "x",
-        "upgrade",
-        "update",
-        "up",
-        "upgrade-self",
-        "clean-install",
-        "ci",
-        "list",
-        "ls",
-        "info",
-        "env",
-        "watch",
-        "w",
-        "clean",
-        "view",
-        "help",
-    ];
+    let all_commands = COMMANDS.iter().flat_map(|spec| spec.names.iter());
 
     let mut similarities: Vec<(String, usize)> = all_commands
-        .iter()
         .map(|&cmd| (cmd.to_string(), levenshtein_distance(input, cmd)))
         .collect();
 
@@ -99,6 +276,25 @@ pub struct Cli {
     pub command: Commands,
 }
 
+/// What `kn cache` does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheAction {
+    List,
+    /// Drop superseded entries and those of deleted projects
+    Prune,
+    Clear,
+}
+
+/// What `kn watch` does with a run that is still going when files change
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WatchMode {
+    /// Stop it and start again
+    #[default]
+    Restart,
+    /// Let it finish, then run once more
+    Queue,
+}
+
 #[derive(Debug)]
 pub enum Commands {
     Install {
@@ -112,6 +308,43 @@ pub enum Commands {
         script_name: Option<String>,
         args: Vec<String>,
         if_present: bool,
+        /// Run the workspace root's script instead of the nearest package's
+        root: bool,
+        /// Ignore cached results of the script
+        force: bool,
+        /// Write the output to this file
+        log: Option<PathBuf>,
+        env: EnvOptions,
+    },
+    RunLast,
+    History {
+        all: bool,
+        limit: usize,
+        json: bool,
+    },
+    RunRecursive {
+        script_name: String,
+        args: Vec<String>,
+        filters: Vec<String>,
+        since: Option<String>,
+        parallel: bool,
+        max_parallel: Option<usize>,
+        continue_on_error: bool,
+        if_present: bool,
+        log: Option<PathBuf>,
+        env: EnvOptions,
+    },
+    RunMany {
+        scripts: Vec<String>,
+        /// Arguments after `--`, passed to every script
+        args: Vec<String>,
+        parallel: bool,
+        max_parallel: Option<usize>,
+        continue_on_error: bool,
+        if_present: bool,
+        root: bool,
+        log: Option<PathBuf>,
+        env: EnvOptions,
     },
     Uninstall {
         packages: Vec<String>,
@@ -120,6 +353,7 @@ pub enum Commands {
     Execute {
         command: String,
         args: Vec<String>,
+        env: EnvOptions,
     },
     Upgrade {
         packages: Vec<String>,
@@ -137,19 +371,56 @@ pub enum Commands {
     Info {
         verbose: bool,
     },
+    Env {
+        env: EnvOptions,
+        all: bool,
+    },
+    Cache {
+        action: CacheAction,
+        all: bool,
+    },
     Watch {
-        script_name: String,
+        /// Without a script, `on` or the `kn.watch` config says what to run
+        script_name: Option<String>,
         patterns: Vec<String>,
+        /// Patterns of files whose changes are ignored
+        ignore: Vec<String>,
+        mode: WatchMode,
+        /// Walk the tree instead of using native file events
+        poll: bool,
+        /// Milliseconds without events before a change is acted on
+        debounce: Option<u64>,
+        /// Scripts to run for changes to other patterns
+        on: Vec<WatchRule>,
     },
     Clean {
         cache: bool,
         all: bool,
         global: bool,
+        /// Show what would be removed without removing anything
+        dry_run: bool,
+        /// Skip confirmation, required when not interactive
+        yes: bool,
+        /// Clean every workspace package
+        recursive: bool,
+        /// Find `node_modules` anywhere below this directory instead
+        scan: Option<PathBuf>,
+        /// With `scan`, only projects unchanged for this long
+        older_than: Option<Duration>,
+        /// With `cache` or `all`, clean every installed manager's cache
+        all_agents: bool,
     },
     View {
         package: String,
         version: Option<String>,
     },
+    Completions {
+        shell: Shell,
+    },
+    /// Dynamic values for shell completion (`kn __complete <kind>`)
+    Complete {
+        kind: String,
+    },
     Help,
     Version,
 }
@@ -217,10 +488,14 @@ impl Cli {
                 i += 1;
                 parse_list_command(&args, &mut i)?
             }
-            "info" | "env" => {
+            "info" => {
                 i += 1;
                 parse_info_command(&args, &mut i)?
             }
+            "env" => {
+                i += 1;
+                parse_env_command(&args, &mut i)?
+            }
             "watch" | "w" => {
                 i += 1;
                 parse_watch_command(&args, &mut i)?
@@ -233,6 +508,21 @@ impl Cli {
                 i += 1;
                 parse_view_command(&args, &mut i)?
             }
+            "history" => {
+                i += 1;
+                parse_history_command(&args, &mut i)?
+            }
+            "cache" => {
+                i += 1;
+                parse_cache_command(&args, &mut i)?
+            }
+            "completions" => {
+                i += 1;
+                parse_completions_command(&args, &mut i)?
+            }
+            "__complete" => Commands::Complete {
+                kind: args.get(i + 1).cloned().unwrap_or_default(),
+            },
             "help" | "--help" | "-h" => Commands::Help,
             "--version" | "-v" | "-V" => Commands::Version,
             _ => return Err(format_unknown_command_error(&args[i])),
@@ -280,23 +570,86 @@ fn parse_run_command(args: &[String], i: &mut usize) -> Result<Commands, String>
     let mut script_name = None;
     let mut script_args = Vec::new();
     let mut if_present = false;
+    // Some(true) for -p, Some(false) for -s
+    let mut parallel: Option<bool> = None;
+    let mut max_parallel = None;
+    let mut continue_on_error = false;
+    let mut scripts = Vec::new();
+    let mut recursive = false;
+    let mut filters = Vec::new();
+    let mut since = None;
+    let mut env = EnvOptions::default();
+    let mut separated = false;
+    let mut root = false;
+    let mut force = false;
+    let mut log = None;
+
+    if args.get(*i).map(String::as_str) == Some("--last") {
+        if *i + 1 < args.len() {
+            return Err(format_error_with_suggestion(
+                "--last takes no script or arguments",
+                "Usage: kn run --last",
+            ));
+        }
+        *i += 1;
+        return Ok(Commands::RunLast);
+    }
 
     while *i < args.len() {
         let arg = args[*i].as_str();
 
-        // Once we have a script name, all remaining args (including flags) go to the script
-        if script_name.is_some() {
-            script_args.push(args[*i].clone());
+        // Once we have a script name, all remaining args (including flags) go to the script.
+        // A `--` right after the name only marks where they start.
+        if script_name.is_some() || separated {
+            if arg == "--" && script_args.is_empty() && !separated {
+                separated = true;
+            } else {
+                script_args.push(args[*i].clone());
+            }
         } else {
             // Before script name, only parse kn's own flags
             match arg {
                 "--if-present" => if_present = true,
+                "-p" | "--parallel" => parallel = Some(true),
+                "-s" | "--sequential" => parallel = Some(false),
+                "--continue-on-error" => continue_on_error = true,
+                "-r" | "--recursive" => recursive = true,
+                "--root" => root = true,
+                "--force" => force = true,
+                _ if parse_env_flag(args, i, &mut env)? => {}
+                "--log" => log = Some(PathBuf::from(take_value(args, i, arg)?)),
+                "--filter" | "--since" => {
+                    let value = take_value(args, i, arg)?;
+                    if arg == "--filter" {
+                        filters.push(value);
+                    } else {
+                        since = Some(value);
+                    }
+                }
+                // `kn run -p a b -- --flag`: the rest goes to every script
+                "--" if parallel.is_some() && !recursive => separated = true,
+                "--max-parallel" => {
+                    *i += 1;
+                    let value = args.get(*i).and_then(|v| v.parse::<usize>().ok());
+                    match value {
+                        Some(n) if n > 0 => max_parallel = Some(n),
+                        _ => {
+                            return Err(format_error_with_suggestion(
+                                "--max-parallel expects a positive number",
+                                "Usage: kn run -p --max-parallel <n> <scripts...>",
+                            ))
+                        }
+                    }
+                    parallel.get_or_insert(true);
+                }
                 arg if arg.starts_with('-') => {
                     return Err(format_error_with_suggestion(
                         &format!("Unknown flag for run: {}", arg),
-                        "Valid flag: --if-present (must come before script name)",
+                        "Valid flags: --last, --if-present, -p (--parallel), -s (--sequential), --max-parallel <n>, --continue-on-error, -r (--recursive), --root, --force, --log <file>, --filter <pattern>, --since <ref>, --env-file <path>, --mode <mode>, --no-env",
                     ));
                 }
+                // With -p/-s every positional argument is a script name
+                _ if parallel.is_some() && !recursive => scripts.push(args[*i].clone()),
                 _ => {
                     // This is the script name
                     script_name = Some(args[*i].clone());
@@ -306,13 +659,95 @@ fn parse_run_command(args: &[String], i: &mut usize) -> Result<Commands, String>
         *i += 1;
     }
 
+    if force && (recursive || parallel.is_some()) {
+        return Err(format_error_with_suggestion(
+            "--force applies to a single script",
+            "Usage: kn run --force <script>",
+        ));
+    }
+
+    if recursive && root {
+        return Err(format_error_with_suggestion(
+            "--root cannot be combined with --recursive",
+            "Usage: kn run --root <script>",
+        ));
+    }
+
+    if recursive {
+        let Some(script_name) = script_name else {
+            return Err(format_error_with_suggestion(
+                "Expected a script name",
+                "Usage: kn run --recursive [--filter <pattern>] [--since <ref>] <script>",
+            ));
+        };
+        return Ok(Commands::RunRecursive {
+            script_name,
+            args: script_args,
+            filters,
+            since,
+            parallel: parallel.unwrap_or(true),
+            max_parallel,
+            continue_on_error,
+            if_present,
+            log,
+            env,
+        });
+    }
+
+    if !filters.is_empty() || since.is_some() {
+        return Err(format_error_with_suggestion(
+            "--filter and --since require --recursive",
+            "Usage: kn run --recursive --filter <pattern> <script>",
+        ));
+    }
+
+    if let Some(parallel) = parallel {
+        if scripts.is_empty() {
+            return Err(format_error_with_suggestion(
+                "Expected at least one script name",
+                "Usage: kn run -p <scripts...>",
+            ));
+        }
+        return Ok(Commands::RunMany {
+            scripts,
+            args: script_args,
+            parallel,
+            max_parallel,
+            continue_on_error,
+            if_present,
+            root,
+            log,
+            env,
+        });
+    }
+
+    if continue_on_error {
+        return Err(format_error_with_suggestion(
+            "--continue-on-error requires -p or -s",
+            "Usage: kn run -p --continue-on-error <scripts...>",
+        ));
+    }
+
     Ok(Commands::Run {
         script_name,
         args: script_args,
         if_present,
+        root,
+        force,
+        log,
+        env,
     })
 }
 
+/// Take the value following flag `name` at `args[*i]`
+fn take_value(args: &[String], i: &mut usize, name: &str) -> Result<String, String> {
+    *i += 1;
+    match args.get(*i) {
+        Some(value) => Ok(value.clone()),
+        None => Err(format_error(&format!("{} expects a value", name))),
+    }
+}
+
 fn parse_uninstall_command(args: &[String], i: &mut usize) -> Result<Commands, String> {
     let mut packages = Vec::new();
     let mut global = false;
@@ -342,6 +777,11 @@ fn parse_uninstall_command(args: &[String], i: &mut usize) -> Result<Commands, S
 }
 
 fn parse_execute_command(args: &[String], i: &mut usize) -> Result<Commands, String> {
+    let mut env = EnvOptions::default();
+    while *i < args.len() && parse_env_flag(args, i, &mut env)? {
+        *i += 1;
+    }
+
     if *i >= args.len() {
         return Err(format_error_with_suggestion(
             "Execute command requires a command to execute",
@@ -361,6 +801,7 @@ fn parse_execute_command(args: &[String], i: &mut usize) -> Result<Commands, Str
     Ok(Commands::Execute {
         command,
         args: exec_args,
+        env,
     })
 }
 
@@ -469,25 +910,64 @@ fn parse_info_command(args: &[String], i: &mut usize) -> Result<Commands, String
 }
 
 fn parse_watch_command(args: &[String], i: &mut usize) -> Result<Commands, String> {
-    if *i >= args.len() {
-        return Err(format_error_with_suggestion(
-            "Watch command requires a script name",
-            "Usage: kn watch <script-name> [patterns...]",
-        ));
-    }
-
-    let script_name = args[*i].clone();
-    *i += 1;
-
+    let mut script_name = None;
     let mut patterns = Vec::new();
+    let mut ignore = Vec::new();
+    let mut mode = WatchMode::default();
+    let mut poll = false;
+    let mut debounce = None;
+    let mut on = Vec::new();
+
     while *i < args.len() {
-        patterns.push(args[*i].clone());
+        match args[*i].as_str() {
+            "--restart" => mode = WatchMode::Restart,
+            "--queue" => mode = WatchMode::Queue,
+            "--ignore" => ignore.push(take_value(args, i, "--ignore")?),
+            "--poll" => poll = true,
+            "--debounce" => {
+                let value = take_value(args, i, "--debounce")?;
+                match value.parse::<u64>() {
+                    Ok(ms) => debounce = Some(ms),
+                    Err(_) => {
+                        return Err(format_error_with_suggestion(
+                            "--debounce expects a number of milliseconds",
+                            "Usage: kn watch --debounce <ms> <script-name>",
+                        ))
+                    }
+                }
+            }
+            "--on" => {
+                let value = take_value(args, i, "--on")?;
+                match WatchRule::parse(&value) {
+                    Some(rule) => on.push(rule),
+                    None => {
+                        return Err(format_error_with_suggestion(
+                            &format!("Invalid --on value: {}", value),
+                            "Usage: kn watch --on <glob>=<script> [--on <glob>=<script>...]",
+                        ))
+                    }
+                }
+            }
+            arg if arg.starts_with('-') => {
+                return Err(format_error_with_suggestion(
+                    &format!("Unknown flag for watch: {}", arg),
+                    "Valid flags: --restart, --queue, --ignore <pattern>, --poll, --debounce <ms>, --on <glob>=<script>",
+                ));
+            }
+            arg if script_name.is_none() => script_name = Some(arg.to_string()),
+            arg => patterns.push(arg.to_string()),
+        }
         *i += 1;
     }
 
     Ok(Commands::Watch {
         script_name,
         patterns,
+        ignore,
+        mode,
+        poll,
+        debounce,
+        on,
     })
 }
 
@@ -495,12 +975,35 @@ fn parse_clean_command(args: &[String], i: &mut usize) -> Result<Commands, Strin
     let mut cache = false;
     let mut all = false;
     let mut global = false;
+    let mut dry_run = false;
+    let mut yes = false;
+    let mut recursive = false;
+    let mut scan = None;
+    let mut older_than = None;
+    let mut all_agents = false;
 
     while *i < args.len() {
         match args[*i].as_str() {
             "--cache" => cache = true,
             "--all" => all = true,
             "--global" | "-g" => global = true,
+            "--dry-run" => dry_run = true,
+            "--yes" | "-y" => yes = true,
+            "--recursive" | "-r" => recursive = true,
+            "--all-agents" => all_agents = true,
+            "--scan" => scan = Some(PathBuf::from(take_value(args, i, "--scan")?)),
+            "--older-than" => {
+                let value = take_value(args, i, "--older-than")?;
+                match parse_age(&value) {
+                    Some(age) => older_than = Some(age),
+                    None => {
+                        return Err(format_error_with_suggestion(
+                            &format!("Invalid --older-than value: {}", value),
+                            "Use a number of hours, days or weeks, like 12h, 30d or 2w",
+                        ))
+                    }
+                }
+            }
             arg if arg.starts_with('-') => {
                 return Err(format_error(&format!("Unknown flag for clean: {}", arg)));
             }
@@ -514,7 +1017,178 @@ fn parse_clean_command(args: &[String], i: &mut usize) -> Result<Commands, Strin
         *i += 1;
     }
 
-    Ok(Commands::Clean { cache, all, global })
+    if older_than.is_some() && scan.is_none() {
+        return Err(format_error_with_suggestion(
+            "--older-than only applies to --scan",
+            "Usage: kn clean --scan <dir> --older-than 30d",
+        ));
+    }
+
+    if all_agents && !cache && !all {
+        return Err(format_error_with_suggestion(
+            "--all-agents only applies to --cache and --all",
+            "Usage: kn clean --cache --all-agents",
+        ));
+    }
+
+    Ok(Commands::Clean {
+        cache,
+        all,
+        global,
+        dry_run,
+        yes,
+        recursive,
+        scan,
+        older_than,
+        all_agents,
+    })
+}
+
+/// An age such as `12h`, `30d` or `2w`
+fn parse_age(value: &str) -> Option<Duration> {
+    let unit = match value.chars().last()? {
+        'h' => 3600,
+        'd' => 86400,
+        'w' => 7 * 86400,
+        _ => return None,
+    };
+    let count: u64 = value[..value.len() - 1].parse().ok()?;
+    Some(Duration::from_secs(count.checked_mul(unit)?))
+}
+
+fn parse_env_command(args: &[String], i: &mut usize) -> Result<Commands, String> {
+    let mut env = EnvOptions::default();
+    let mut all = false;
+
+    while *i < args.len() {
+        match args[*i].as_str() {
+            "--all" | "-a" => all = true,
+            _ if parse_env_flag(args, i, &mut env)? => {}
+            arg if arg.starts_with('-') => {
+                return Err(format_error_with_suggestion(
+                    &format!("Unknown flag for env: {}", arg),
+                    "Valid flags: -a (--all), --env-file <path>, --mode <mode>",
+                ));
+            }
+            _ => {
+                return Err(format_error(&format!(
+                    "Unexpected argument for env: {}",
+                    args[*i]
+                )))
+            }
+        }
+        *i += 1;
+    }
+
+    Ok(Commands::Env { env, all })
+}
+
+/// Parse a `.env` loading flag shared by run, exec and env. Returns whether
+/// `args[*i]` was one.
+fn parse_env_flag(args: &[String], i: &mut usize, env: &mut EnvOptions) -> Result<bool, String> {
+    match args[*i].as_str() {
+        "--env-file" => env
+            .files
+            .push(PathBuf::from(take_value(args, i, "--env-file")?)),
+        "--mode" => env.mode = Some(take_value(args, i, "--mode")?),
+        "--no-env" => env.disabled = true,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+fn parse_history_command(args: &[String], i: &mut usize) -> Result<Commands, String> {
+    let mut all = false;
+    let mut limit = 20;
+    let mut json = false;
+
+    while *i < args.len() {
+        match args[*i].as_str() {
+            "--all" | "-a" => all = true,
+            "--json" => json = true,
+            "--limit" | "-n" => {
+                *i += 1;
+                match args.get(*i).and_then(|v| v.parse::<usize>().ok()) {
+                    Some(n) => limit = n,
+                    None => {
+                        return Err(format_error_with_suggestion(
+                            "--limit expects a number",
+                            "Usage: kn history --limit <n>",
+                        ))
+                    }
+                }
+            }
+            arg if arg.starts_with('-') => {
+                return Err(format_error_with_suggestion(
+                    &format!("Unknown flag for history: {}", arg),
+                    "Valid flags: -a (--all), -n (--limit) <n>, --json",
+                ));
+            }
+            _ => {
+                return Err(format_error(&format!(
+                    "Unexpected argument for history: {}",
+                    args[*i]
+                )))
+            }
+        }
+        *i += 1;
+    }
+
+    Ok(Commands::History { all, limit, json })
+}
+
+fn parse_cache_command(args: &[String], i: &mut usize) -> Result<Commands, String> {
+    let mut action = CacheAction::List;
+    let mut all = false;
+
+    while *i < args.len() {
+        match args[*i].as_str() {
+            "list" | "ls" => action = CacheAction::List,
+            "prune" => action = CacheAction::Prune,
+            "clear" => action = CacheAction::Clear,
+            "--all" | "-a" => all = true,
+            arg if arg.starts_with('-') => {
+                return Err(format_error_with_suggestion(
+                    &format!("Unknown flag for cache: {}", arg),
+                    "Valid flags: -a (--all)",
+                ));
+            }
+            arg => {
+                return Err(format_error_with_suggestion(
+                    &format!("Unknown cache command: {}", arg),
+                    "Usage: kn cache [list|prune|clear] [--all]",
+                ))
+            }
+        }
+        *i += 1;
+    }
+
+    Ok(Commands::Cache { action, all })
+}
+
+fn parse_completions_command(args: &[String], i: &mut usize) -> Result<Commands, String> {
+    let usage = format!("Usage: kn completions <{}>", SHELLS.join("|"));
+    let Some(name) = args.get(*i) else {
+        return Err(format_error_with_suggestion(
+            "Completions command requires a shell",
+            &usage,
+        ));
+    };
+    let Some(shell) = Shell::from_name(name) else {
+        return Err(format_error_with_suggestion(
+            &format!("Unsupported shell: {}", name),
+            &usage,
+        ));
+    };
+    *i += 1;
+
+    if let Some(arg) = args.get(*i) {
+        return Err(format_error(&format!(
+            "Unexpected argument for completions: {}",
+            arg
+        )));
+    }
+    Ok(Commands::Completions { shell })
 }
 
 fn parse_view_command(args: &[String], i: &mut usize) -> Result<Commands, String> {
@@ -538,3 +1212,53 @@ fn parse_view_command(args: &[String], i: &mut usize) -> Result<Commands, String
 
     Ok(Commands::View { package, version })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_run(args: &[&str]) -> Result<Commands, String> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        parse_run_command(&args, &mut 0)
+    }
+
+    #[test]
+    fn test_run_args_after_script_go_to_script() {
+        match parse_run(&["dev", "-r", "dotenv/config"]) {
+            Ok(Commands::Run {
+                script_name, args, ..
+            }) => {
+                assert_eq!(script_name.as_deref(), Some("dev"));
+                assert_eq!(args, ["-r", "dotenv/config"]);
+            }
+            other => panic!("Expected a single run, got {:?}", other),
+        }
+
+        match parse_run(&["-r", "--filter", "web", "build", "--watch"]) {
+            Ok(Commands::RunRecursive {
+                script_name,
+                args,
+                filters,
+                ..
+            }) => {
+                assert_eq!(script_name, "build");
+                assert_eq!(args, ["--watch"]);
+                assert_eq!(filters, ["web"]);
+            }
+            other => panic!("Expected a recursive run, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_run_many_args_after_separator() {
+        for mode in ["-p", "-s"] {
+            match parse_run(&[mode, "a", "b", "--", "--flag", "--", "x"]) {
+                Ok(Commands::RunMany { scripts, args, .. }) => {
+                    assert_eq!(scripts, ["a", "b"]);
+                    assert_eq!(args, ["--flag", "--", "x"]);
+                }
+                other => panic!("Expected a multi-script run, got {:?}", other),
+            }
+        }
+    }
+}
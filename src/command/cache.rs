@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use console::style;
+use std::path::PathBuf;
+
+use crate::cache::{superseded, Cache, CacheEntry};
+use crate::cli_parser::CacheAction;
+use crate::display::StyledOutput;
+use crate::history::time_ago;
+use crate::utils::{find_and_parse_package_json, format_size};
+
+pub fn handle(action: CacheAction, all: bool) -> Result<()> {
+    let cache = Cache::open().context("Could not determine the cache directory")?;
+    let mut entries = cache.entries();
+
+    if action == CacheAction::Prune {
+        // Superseded runs and runs of deleted projects, in every project
+        let mut stale = superseded(&entries);
+        for entry in entries.iter().filter(|e| !e.project.is_dir()) {
+            if !stale.iter().any(|s| s.hash == entry.hash) {
+                stale.push(entry);
+            }
+        }
+        return remove(&cache, &stale);
+    }
+
+    if !all {
+        let project = project_dir()?;
+        entries.retain(|e| e.project == project);
+    }
+    match action {
+        CacheAction::Clear => remove(&cache, &entries.iter().collect::<Vec<_>>()),
+        _ => list(&cache, &entries, all),
+    }
+}
+
+fn list(cache: &Cache, entries: &[CacheEntry], all: bool) -> Result<()> {
+    if entries.is_empty() {
+        StyledOutput::info("No cached script runs");
+        StyledOutput::hint("Configure scripts to cache in the kn.cache section of package.json");
+        return Ok(());
+    }
+
+    let mut total = 0;
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|entry| {
+            let size = cache.size(entry);
+            total += size;
+            let mut row = vec![
+                entry.script.clone(),
+                entry.outputs.len().to_string(),
+                format_size(size),
+                StyledOutput::duration(entry.duration_ms as f64 / 1000.0),
+                style(time_ago(entry.timestamp)).dim().to_string(),
+                style(&entry.hash[..12]).dim().to_string(),
+            ];
+            if all {
+                row.push(style(entry.project.display()).dim().to_string());
+            }
+            row
+        })
+        .collect();
+
+    let mut headers = vec!["Script", "Outputs", "Size", "Took", "When", "Hash"];
+    if all {
+        headers.push("Project");
+    }
+
+    println!();
+    StyledOutput::titled("Cached runs");
+    println!();
+    StyledOutput::table(&headers, &rows);
+    println!();
+    StyledOutput::hint(&format!(
+        "{} in total · kn cache prune drops superseded runs",
+        format_size(total)
+    ));
+    println!();
+    Ok(())
+}
+
+fn remove(cache: &Cache, entries: &[&CacheEntry]) -> Result<()> {
+    if entries.is_empty() {
+        StyledOutput::info("Nothing to remove");
+        return Ok(());
+    }
+
+    let mut freed = 0;
+    for entry in entries {
+        freed += cache.remove(entry)?;
+    }
+    StyledOutput::success(&format!(
+        "Removed {} cached {} · freed {}",
+        entries.len(),
+        if entries.len() == 1 { "run" } else { "runs" },
+        format_size(freed)
+    ));
+    Ok(())
+}
+
+/// Directory of the nearest package.json, which identifies the project
+fn project_dir() -> Result<PathBuf> {
+    let cwd = std::env::current_dir()?;
+    let (path, _package) = find_and_parse_package_json(&cwd)?;
+    Ok(path.parent().map(PathBuf::from).unwrap_or(cwd))
+}
@@ -1,28 +1,89 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use console::style;
+use inquire::{Confirm, InquireError, MultiSelect};
 
+use crate::agent_cache::{self, cache_dir, installed_agents};
 use crate::agents::Agent;
+use crate::artifacts::{default_targets, find_candidates, package_targets, Candidate, Safety};
 use crate::detect::detect;
 use crate::display::StyledOutput;
+use crate::error::KnError;
+use crate::glob::relative_path;
+use crate::history::time_ago;
 use crate::runner::DetectOptions;
-use crate::utils::{dir_size, format_size};
+use crate::scan::{dir_sizes, find_node_modules, Install};
+use crate::utils::{format_size, is_interactive};
+use crate::workspace;
+use serde_json::Value;
 use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-pub fn handle(cache: bool, all: bool, global: bool) -> Result<()> {
-    if all {
-        clean_all()?;
+/// Options of `kn clean`
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CleanOptions {
+    /// Show what would be removed without removing anything
+    pub dry_run: bool,
+    /// Skip confirmation, required when not interactive
+    pub yes: bool,
+    /// Clean every package of the workspace
+    pub recursive: bool,
+    /// Clean the caches of every installed package manager
+    pub all_agents: bool,
+}
+
+/// Options of `kn clean --scan`
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Where to look for `node_modules`
+    pub dir: PathBuf,
+    /// Only list projects unchanged for this long
+    pub older_than: Option<Duration>,
+}
+
+/// Candidates found in one package
+struct Group {
+    /// Package name, `None` when cleaning the current directory only
+    name: Option<String>,
+    candidates: Vec<Candidate>,
+}
+
+pub fn handle(
+    cache: bool,
+    all: bool,
+    global: bool,
+    scan: Option<ScanOptions>,
+    options: CleanOptions,
+) -> Result<()> {
+    if let Some(scan) = scan {
+        clean_scan(&scan, options)?;
+    } else if all {
+        clean_all(options)?;
     } else if cache {
-        clean_cache(false)?;
+        clean_cache(options, false)?;
     } else if global {
         clean_global()?;
     } else {
-        clean_local(false)?;
+        clean_local(options, false)?;
     }
 
     Ok(())
 }
 
+/// Refuse to delete local directories without `--yes` when there is no
+/// terminal to confirm in
+fn require_confirmation(options: CleanOptions) -> Result<()> {
+    if !options.dry_run && !options.yes && !is_interactive() {
+        bail!(
+            "kn clean needs confirmation when not run in a terminal\n    {} {}",
+            style("└").dim(),
+            style("Pass --yes to clean, or --dry-run to see what would be removed").dim(),
+        );
+    }
+    Ok(())
+}
+
 fn detect_current_agent() -> Agent {
     let options = DetectOptions {
         cwd: std::env::current_dir().unwrap_or_default(),
@@ -31,59 +92,211 @@ fn detect_current_agent() -> Agent {
     detect(options).unwrap_or(Agent::Npm)
 }
 
-/// Clean local build artifacts. When `quiet`, skip output (used in clean_all).
-fn clean_local(quiet: bool) -> Result<(u32, u64)> {
+/// Targets of the package in `dir`: its `kn.clean` field, else `fallback`
+fn targets_of(dir: &Path, fallback: &[String]) -> Result<Vec<String>> {
+    let manifest = dir.join("package.json");
+    if !manifest.exists() {
+        return Ok(fallback.to_vec());
+    }
+    Ok(package_targets(&manifest)?.unwrap_or_else(|| fallback.to_vec()))
+}
+
+/// Candidates of the current directory, or with `recursive` of the
+/// workspace root and every workspace package
+fn scan(cwd: &Path, recursive: bool) -> Result<Vec<Group>> {
+    if !recursive {
+        let targets = targets_of(cwd, &default_targets())?;
+        return Ok(vec![Group {
+            name: None,
+            candidates: find_candidates(cwd, &targets, &[]),
+        }]);
+    }
+
+    let Some(root) = workspace::find_root(cwd) else {
+        bail!(
+            "No workspace found\n    {} {}",
+            style("└").dim(),
+            style("Add a workspaces field to package.json or a pnpm-workspace.yaml").dim(),
+        );
+    };
+    let packages = workspace::discover(&root)?;
+    let root_targets = targets_of(&root, &default_targets())?;
+    let root_name = fs::read_to_string(root.join("package.json"))
+        .ok()
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+        .and_then(|json| json["name"].as_str().map(str::to_string))
+        .unwrap_or_else(|| "(root)".to_string());
+
+    // Each package is cleaned on its own, never as part of one above it
+    let nested = |dir: &Path| -> Vec<PathBuf> {
+        packages
+            .iter()
+            .filter(|p| p.dir != dir && p.dir.starts_with(dir))
+            .map(|p| p.dir.clone())
+            .collect()
+    };
+
+    let mut groups = vec![Group {
+        name: Some(root_name),
+        candidates: find_candidates(&root, &root_targets, &nested(&root)),
+    }];
+    for package in &packages {
+        let targets = targets_of(&package.dir, &root_targets)?;
+        groups.push(Group {
+            name: Some(package.name.clone()),
+            candidates: find_candidates(&package.dir, &targets, &nested(&package.dir)),
+        });
+    }
+    Ok(groups)
+}
+
+/// Clean local build artifacts. When `quiet`, skip the scan spinner and the
+/// result listing (used in clean_all); the plan and prompts still show.
+/// Candidates holding tracked files are never removed, and ones git does
+/// not ignore only after confirming each.
+fn clean_local(options: CleanOptions, quiet: bool) -> Result<(u32, u64)> {
+    require_confirmation(options)?;
     let spinner = if !quiet {
         Some(StyledOutput::working("Scanning local artifacts..."))
     } else {
         None
     };
+    let cwd = std::env::current_dir()?;
+    let scanned = scan(&cwd, options.recursive);
+    drop(spinner);
+    let groups: Vec<Group> = scanned?
+        .into_iter()
+        .filter(|group| !group.candidates.is_empty())
+        .collect();
 
-    let paths = [
-        "node_modules",
-        ".turbo",
-        ".next",
-        "dist",
-        "build",
-        ".vite",
-        ".nuxt",
-    ];
+    if groups.is_empty() {
+        if !quiet {
+            StyledOutput::info("Nothing to clean");
+            println!();
+        }
+        return Ok((0, 0));
+    }
 
-    let mut removed = 0u32;
-    let mut size_freed = 0u64;
-    let mut removed_names = Vec::new();
+    let interactive = !options.yes && is_interactive();
+    if options.dry_run || interactive {
+        show_plan(&groups, options.dry_run);
+    }
+    if options.dry_run {
+        let size: u64 = groups
+            .iter()
+            .flat_map(|g| &g.candidates)
+            .filter(|c| c.safety == Safety::Artifact)
+            .map(|c| c.size)
+            .sum();
+        StyledOutput::info(&format!("Would free ~{}", format_size(size)));
+        println!();
+        return Ok((0, 0));
+    }
 
-    for path in paths {
-        if let Ok(metadata) = fs::metadata(path) {
-            if metadata.is_dir() {
-                if let Ok(size) = dir_size(std::path::Path::new(path)) {
-                    size_freed += size;
+    // Decide what goes: artifacts, and unignored candidates confirmed one
+    // by one. Entries are (group index, candidate).
+    let mut selected: Vec<(usize, &Candidate)> = Vec::new();
+    let mut skipped: Vec<(usize, &Candidate, String)> = Vec::new();
+    for (index, group) in groups.iter().enumerate() {
+        for candidate in &group.candidates {
+            match candidate.safety {
+                Safety::Artifact => selected.push((index, candidate)),
+                Safety::Tracked(count) => {
+                    skipped.push((index, candidate, tracked_files(count)));
                 }
-                if fs::remove_dir_all(path).is_ok() {
-                    removed += 1;
-                    removed_names.push(path);
+                Safety::NotIgnored if interactive => {
+                    let confirmed = Confirm::new(&format!(
+                        "{} is not ignored by git and may hold sources. Remove it anyway?",
+                        style(prompt_name(&cwd, candidate)).yellow(),
+                    ))
+                    .with_default(false)
+                    .prompt()
+                    .unwrap_or(false);
+                    if confirmed {
+                        selected.push((index, candidate));
+                    } else {
+                        skipped.push((index, candidate, "not ignored by git".to_string()));
+                    }
+                }
+                Safety::NotIgnored => {
+                    skipped.push((index, candidate, "not ignored by git".to_string()));
                 }
             }
         }
     }
 
-    drop(spinner);
+    if interactive && !selected.is_empty() {
+        let size: u64 = selected.iter().map(|(_, c)| c.size).sum();
+        let all_dirs = selected.iter().all(|(_, c)| c.is_dir);
+        let confirmed = Confirm::new(&format!(
+            "Remove {} {} (~{})?",
+            selected.len(),
+            match (all_dirs, selected.len()) {
+                (true, 1) => "directory",
+                (true, _) => "directories",
+                (false, 1) => "item",
+                (false, _) => "items",
+            },
+            format_size(size),
+        ))
+        .with_default(true)
+        .prompt()
+        .unwrap_or(false);
+        if !confirmed {
+            return Err(KnError::Cancelled.into());
+        }
+    }
+
+    let mut removed = 0u32;
+    let mut size_freed = 0u64;
+    let mut freed = vec![0u64; groups.len()];
+    let mut results: Vec<Vec<String>> = vec![Vec::new(); groups.len()];
+    for (index, candidate) in selected {
+        let result = if candidate.is_dir {
+            fs::remove_dir_all(&candidate.path)
+        } else {
+            fs::remove_file(&candidate.path)
+        };
+        match result {
+            Ok(()) => {
+                removed += 1;
+                size_freed += candidate.size;
+                freed[index] += candidate.size;
+                results[index].push(format!(
+                    "{} {}",
+                    style(&candidate.name).cyan(),
+                    style("removed").green()
+                ));
+            }
+            Err(e) => results[index].push(format!(
+                "{} {}",
+                style(&candidate.name).cyan(),
+                style(format!("failed: {}", e)).red()
+            )),
+        }
+    }
+    for (index, candidate, reason) in &skipped {
+        results[*index].push(format!(
+            "{} {}",
+            style(&candidate.name).cyan(),
+            style(format!("skipped ({})", reason)).yellow()
+        ));
+    }
 
     if !quiet {
+        println!();
+        println!("  {}", style("Cleaned").bold());
+        let headers: Vec<String> = groups
+            .iter()
+            .zip(&freed)
+            .map(|(group, &size)| group_header(group, size, "freed"))
+            .collect();
+        print_tree(&groups, &headers, &results);
+        println!();
         if removed > 0 {
-            println!();
-            println!("  {}", style("Cleaned").bold());
-            for (i, name) in removed_names.iter().enumerate() {
-                let is_last = i == removed_names.len() - 1;
-                StyledOutput::tree_item(
-                    &format!("{} {}", style(name).cyan(), style("removed").green(),),
-                    is_last,
-                );
-            }
-            println!();
             StyledOutput::success(&format!("Freed ~{}", format_size(size_freed),));
         } else {
-            StyledOutput::info("Nothing to clean");
+            StyledOutput::info("Nothing removed");
         }
         println!();
     }
@@ -91,42 +304,378 @@ fn clean_local(quiet: bool) -> Result<(u32, u64)> {
     Ok((removed, size_freed))
 }
 
-/// Clean package manager cache. When `quiet`, skip output.
-fn clean_cache(quiet: bool) -> Result<bool> {
+/// How a candidate is named in prompts: relative to the current directory
+fn prompt_name(cwd: &Path, candidate: &Candidate) -> String {
+    relative_path(cwd, &candidate.path)
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| candidate.path.display().to_string())
+}
+
+fn tracked_files(count: usize) -> String {
+    if count == 1 {
+        "1 tracked file".to_string()
+    } else {
+        format!("{} tracked files", count)
+    }
+}
+
+/// A package's line in a nested tree: its name and `size` labelled `label`
+fn group_header(group: &Group, size: u64, label: &str) -> String {
+    format!(
+        "{}  {}",
+        style(group.name.as_deref().unwrap_or_default()).bold(),
+        style(format!("{} {}", format_size(size), label).trim_end()).dim(),
+    )
+}
+
+/// Print each group's `lines`, nested under `headers` when the groups are
+/// named packages
+fn print_tree(groups: &[Group], headers: &[String], lines: &[Vec<String>]) {
+    for (i, (group, lines)) in groups.iter().zip(lines).enumerate() {
+        let last_group = i == groups.len() - 1;
+        if group.name.is_none() {
+            for (j, line) in lines.iter().enumerate() {
+                StyledOutput::tree_item(line, last_group && j == lines.len() - 1);
+            }
+            continue;
+        }
+        StyledOutput::tree_item(&headers[i], last_group);
+        for (j, line) in lines.iter().enumerate() {
+            StyledOutput::tree_child(line, last_group, j == lines.len() - 1);
+        }
+    }
+}
+
+/// List the candidates with their sizes and what happens to them
+fn show_plan(groups: &[Group], dry_run: bool) {
+    let width = groups
+        .iter()
+        .flat_map(|g| &g.candidates)
+        .map(|c| c.name.len())
+        .max()
+        .unwrap_or(0);
+
+    let headers: Vec<String> = groups
+        .iter()
+        .map(|group| {
+            let size = group.candidates.iter().map(|c| c.size).sum();
+            group_header(group, size, "")
+        })
+        .collect();
+    let lines: Vec<Vec<String>> = groups
+        .iter()
+        .map(|group| {
+            group
+                .candidates
+                .iter()
+                .map(|candidate| {
+                    let status = match candidate.safety {
+                        Safety::Artifact => style("remove".to_string()).green(),
+                        Safety::Tracked(count) => {
+                            style(format!("skip · {}", tracked_files(count))).yellow()
+                        }
+                        Safety::NotIgnored => {
+                            style("ask · not ignored by git".to_string()).yellow()
+                        }
+                    };
+                    format!(
+                        "{}  {:>10}  {}",
+                        style(format!("{:<width$}", candidate.name)).cyan(),
+                        style(format_size(candidate.size)).dim(),
+                        status,
+                    )
+                })
+                .collect()
+        })
+        .collect();
+
+    println!();
+    println!(
+        "  {}",
+        style(if dry_run { "Would clean" } else { "Clean" }).bold()
+    );
+    print_tree(groups, &headers, &lines);
+    println!();
+}
+
+/// Find `node_modules` below a directory and remove the ones the user
+/// picks, or all of them with `--yes`
+fn clean_scan(scan: &ScanOptions, options: CleanOptions) -> Result<()> {
+    require_confirmation(options)?;
+    if !scan.dir.is_dir() {
+        bail!("Directory not found: {}", scan.dir.display());
+    }
+    let root = scan.dir.canonicalize()?;
+
+    let spinner = StyledOutput::working(&format!(
+        "Scanning {} for node_modules...",
+        scan.dir.display()
+    ));
+    let mut installs = find_node_modules(&root);
+    drop(spinner);
+
+    if let Some(age) = scan.older_than {
+        let cutoff = SystemTime::now().checked_sub(age).unwrap_or(UNIX_EPOCH);
+        installs.retain(|install| install.modified <= cutoff);
+    }
+    if installs.is_empty() {
+        StyledOutput::info(if scan.older_than.is_some() {
+            "No node_modules in projects that old"
+        } else {
+            "No node_modules found"
+        });
+        println!();
+        return Ok(());
+    }
+    installs.sort_by_key(|install| std::cmp::Reverse(install.size));
+
+    let project_name = |install: &Install| {
+        relative_path(&root, install.project())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| ".".to_string())
+    };
+    let age = |install: &Install| {
+        let modified = install
+            .modified
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        time_ago(modified.as_secs())
+    };
+    let total: u64 = installs.iter().map(|install| install.size).sum();
+
+    println!();
+    let rows: Vec<Vec<String>> = installs
+        .iter()
+        .map(|install| {
+            vec![
+                style(project_name(install)).cyan().to_string(),
+                format_size(install.size),
+                style(age(install)).dim().to_string(),
+            ]
+        })
+        .collect();
+    StyledOutput::table(&["Project", "node_modules", "Changed"], &rows);
+    println!();
+    StyledOutput::info(&format!(
+        "{} node_modules, ~{} in total",
+        installs.len(),
+        format_size(total)
+    ));
+    println!();
+
+    if options.dry_run {
+        return Ok(());
+    }
+
+    let selected: Vec<&Install> = if options.yes {
+        installs.iter().collect()
+    } else {
+        // Options are the table's rows, as plain text
+        let width = installs
+            .iter()
+            .map(|install| project_name(install).len())
+            .max()
+            .unwrap_or(0);
+        let options: Vec<String> = installs
+            .iter()
+            .map(|install| {
+                format!(
+                    "{:<width$}  {:>10}  {}",
+                    project_name(install),
+                    format_size(install.size),
+                    age(install),
+                )
+            })
+            .collect();
+        let picked = match MultiSelect::new("Remove node_modules of:", options)
+            .with_page_size(15)
+            .raw_prompt()
+        {
+            Ok(picked) => picked,
+            Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {
+                return Err(KnError::Cancelled.into())
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let selected: Vec<&Install> = picked.iter().map(|o| &installs[o.index]).collect();
+        if selected.is_empty() {
+            StyledOutput::dim("Nothing selected");
+            return Ok(());
+        }
+
+        let size: u64 = selected.iter().map(|install| install.size).sum();
+        let confirmed = Confirm::new(&format!(
+            "Remove {} node_modules (~{})?",
+            selected.len(),
+            format_size(size),
+        ))
+        .with_default(true)
+        .prompt()
+        .unwrap_or(false);
+        if !confirmed {
+            return Err(KnError::Cancelled.into());
+        }
+        selected
+    };
+
+    let mut size_freed = 0u64;
+    let mut results = Vec::new();
+    for install in &selected {
+        let name = style(project_name(install)).cyan();
+        match fs::remove_dir_all(&install.path) {
+            Ok(()) => {
+                size_freed += install.size;
+                results.push(format!("{} {}", name, style("removed").green()));
+            }
+            Err(e) => results.push(format!(
+                "{} {}",
+                name,
+                style(format!("failed: {}", e)).red()
+            )),
+        }
+    }
+
+    println!();
+    println!("  {}", style("Cleaned").bold());
+    for (i, line) in results.iter().enumerate() {
+        StyledOutput::tree_item(line, i == results.len() - 1);
+    }
+    println!();
+    if size_freed > 0 {
+        StyledOutput::success(&format!("Freed ~{}", format_size(size_freed)));
+    } else {
+        StyledOutput::info("Nothing removed");
+    }
+    println!();
+
+    Ok(())
+}
+
+/// Clean package manager caches: the current project's manager, or every
+/// installed one with `--all-agents`. Sizes are measured before and after;
+/// returns what was freed when any cache was cleaned. When `quiet`, skip the
+/// spinners and the result table.
+fn clean_cache(options: CleanOptions, quiet: bool) -> Result<Option<u64>> {
+    let agents = if options.all_agents {
+        installed_agents()
+    } else {
+        vec![detect_current_agent()]
+    };
+    if agents.is_empty() {
+        if !quiet {
+            StyledOutput::info("No package managers found");
+            println!();
+        }
+        return Ok(None);
+    }
+
     let spinner = if !quiet {
-        Some(StyledOutput::working("Cleaning cache..."))
+        Some(StyledOutput::working("Measuring caches..."))
     } else {
         None
     };
+    let dirs: Vec<Option<PathBuf>> = agents.iter().map(|&agent| cache_dir(agent)).collect();
+    let before = measure(&dirs);
+    drop(spinner);
 
-    let agent = detect_current_agent();
+    if options.dry_run {
+        let rows: Vec<Vec<String>> = agents
+            .iter()
+            .zip(&dirs)
+            .zip(&before)
+            .map(|((agent, dir), size)| {
+                let (cmd, args) = agent.cache_clean_args();
+                vec![
+                    style(agent).cyan().to_string(),
+                    style(display_dir(dir.as_deref())).dim().to_string(),
+                    size.map(format_size).unwrap_or_else(|| "?".to_string()),
+                    format!("{} {}", cmd, args.join(" ")),
+                ]
+            })
+            .collect();
+        println!();
+        StyledOutput::table(&["Manager", "Cache", "Size", "Clean with"], &rows);
+        println!();
+        let total: u64 = before.iter().flatten().sum();
+        StyledOutput::info(&format!("Caches hold ~{}", format_size(total)));
+        println!();
+        return Ok(None);
+    }
 
+    let spinner = if !quiet {
+        Some(StyledOutput::working("Cleaning caches..."))
+    } else {
+        None
+    };
+    let results: Vec<Result<()>> = agents
+        .iter()
+        .map(|&agent| agent_cache::clean(agent))
+        .collect();
+    let after = measure(&dirs);
     drop(spinner);
 
-    if let Some((cmd, args)) = agent.cache_clean_args() {
-        let result = Command::new(cmd).args(&args).status();
-
-        match result {
-            Ok(status) if status.success() => {
-                if !quiet {
-                    StyledOutput::success(&format!("{} cache cleaned", agent));
-                    println!();
-                }
-                return Ok(true);
+    let mut freed = 0u64;
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for (i, agent) in agents.iter().enumerate() {
+        let after_cell = match (&results[i], after[i]) {
+            (Err(e), _) => {
+                errors.push(e.to_string());
+                style("failed".to_string()).red().to_string()
             }
-            _ => {
-                if !quiet {
-                    StyledOutput::error(&format!("Failed to clean {} cache", agent));
-                    println!();
-                }
+            (Ok(()), Some(size)) => {
+                freed += before[i].unwrap_or(0).saturating_sub(size);
+                style(format_size(size)).green().to_string()
             }
+            (Ok(()), None) => style("cleaned".to_string()).green().to_string(),
+        };
+        rows.push(vec![
+            style(agent).cyan().to_string(),
+            style(display_dir(dirs[i].as_deref())).dim().to_string(),
+            before[i]
+                .map(format_size)
+                .unwrap_or_else(|| "?".to_string()),
+            after_cell,
+        ]);
+    }
+
+    if !quiet {
+        println!();
+        StyledOutput::table(&["Manager", "Cache", "Before", "After"], &rows);
+        println!();
+        for error in &errors {
+            StyledOutput::error(error);
+        }
+        if freed > 0 {
+            StyledOutput::success(&format!("Freed ~{}", format_size(freed)));
+        } else if errors.len() < agents.len() {
+            StyledOutput::info("Caches cleaned, nothing freed");
         }
-    } else if !quiet {
-        StyledOutput::info(&format!("{} cache cleaning not supported", agent));
         println!();
     }
 
-    Ok(false)
+    let cleaned = results.iter().any(|result| result.is_ok());
+    Ok(cleaned.then_some(freed))
+}
+
+/// Sizes of the cache directories that exist
+fn measure(dirs: &[Option<PathBuf>]) -> Vec<Option<u64>> {
+    let existing: Vec<PathBuf> = dirs.iter().flatten().cloned().collect();
+    let mut sizes = dir_sizes(&existing).into_iter();
+    dirs.iter()
+        .map(|dir| dir.as_ref().and_then(|_| sizes.next()))
+        .collect()
+}
+
+/// A cache directory, under `~` when in the home directory
+fn display_dir(dir: Option<&Path>) -> String {
+    let Some(dir) = dir else {
+        return "unknown".to_string();
+    };
+    match dirs::home_dir().and_then(|home| relative_path(&home, dir)) {
+        Some(relative) if !relative.is_empty() => format!("~/{}", relative),
+        _ => dir.display().to_string(),
+    }
 }
 
 fn clean_global() -> Result<()> {
@@ -142,12 +691,17 @@ fn clean_global() -> Result<()> {
     Ok(())
 }
 
-fn clean_all() -> Result<()> {
-    let spinner = StyledOutput::working("Running deep clean...");
+fn clean_all(options: CleanOptions) -> Result<()> {
+    if options.dry_run {
+        clean_local(options, false)?;
+        clean_cache(options, false)?;
+        return Ok(());
+    }
 
-    let (removed, size_freed) = clean_local(true)?;
-    let cache_ok = clean_cache(true)?;
+    let (removed, size_freed) = clean_local(options, true)?;
 
+    let spinner = StyledOutput::working("Running deep clean...");
+    let cache_freed = clean_cache(options, true)?;
     drop(spinner);
 
     // ── Summary card ──
@@ -162,11 +716,15 @@ fn clean_all() -> Result<()> {
         ));
     }
 
-    if cache_ok {
-        lines.push(format!("{} Cache cleaned", style("✔").green(),));
+    if let Some(freed) = cache_freed {
+        lines.push(format!(
+            "{} Cache cleaned, ~{} freed",
+            style("✔").green(),
+            format_size(freed),
+        ));
     }
 
-    if removed == 0 && !cache_ok {
+    if removed == 0 && cache_freed.is_none() {
         lines.push(format!("{}", style("Nothing to clean").dim()));
     }
 
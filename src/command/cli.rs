@@ -1,10 +1,13 @@
 use crate::cli_parser::{Cli, Commands};
+use crate::command::run::{RunOptions, WorkspaceSelection};
 use crate::command::{
-    clean, clean_install, execute, info, install, list, run, uninstall, upgrade, upgrade_self,
-    view, watch,
+    cache, clean, clean_install, completions, env, execute, history, info, install, list, run,
+    uninstall, upgrade, upgrade_self, view, watch,
 };
 use crate::display::StyledOutput;
+use crate::multi_run::MultiRunOptions;
 use anyhow::Result;
+use std::time::Duration;
 
 impl Cli {
     pub fn execute(self) -> Result<()> {
@@ -25,9 +28,80 @@ impl Cli {
                 script_name,
                 args,
                 if_present,
-            } => run::handle(script_name, args, if_present),
+                root,
+                force,
+                log,
+                env,
+            } => run::handle(
+                script_name,
+                args,
+                &RunOptions {
+                    if_present,
+                    root,
+                    force,
+                    log,
+                    env,
+                },
+            ),
+            Commands::RunLast => run::handle_last(),
+            Commands::History { all, limit, json } => history::handle(all, limit, json),
+            Commands::RunRecursive {
+                script_name,
+                args,
+                filters,
+                since,
+                parallel,
+                max_parallel,
+                continue_on_error,
+                if_present,
+                log,
+                env,
+            } => run::handle_recursive(
+                &script_name,
+                &args,
+                &WorkspaceSelection { filters, since },
+                MultiRunOptions {
+                    parallel,
+                    max_parallel,
+                    continue_on_error,
+                    ..Default::default()
+                },
+                &RunOptions {
+                    if_present,
+                    log,
+                    env,
+                    ..Default::default()
+                },
+            ),
+            Commands::RunMany {
+                scripts,
+                args,
+                parallel,
+                max_parallel,
+                continue_on_error,
+                if_present,
+                root,
+                log,
+                env,
+            } => run::handle_many(
+                scripts,
+                &args,
+                MultiRunOptions {
+                    parallel,
+                    max_parallel,
+                    continue_on_error,
+                    ..Default::default()
+                },
+                &RunOptions {
+                    if_present,
+                    root,
+                    log,
+                    env,
+                    ..Default::default()
+                },
+            ),
             Commands::Uninstall { packages, global } => uninstall::handle(packages, global),
-            Commands::Execute { command, args } => execute::handle(command, args),
+            Commands::Execute { command, args, env } => execute::handle(command, args, env),
             Commands::Upgrade {
                 packages,
                 interactive,
@@ -39,12 +113,52 @@ impl Cli {
             }
             Commands::List { json } => list::handle(json),
             Commands::Info { verbose } => info::handle(verbose),
+            Commands::Env { env: options, all } => env::handle(&options, all),
+            Commands::Cache { action, all } => cache::handle(action, all),
             Commands::Watch {
                 script_name,
                 patterns,
-            } => watch::handle(script_name, patterns),
-            Commands::Clean { cache, all, global } => clean::handle(cache, all, global),
+                ignore,
+                mode,
+                poll,
+                debounce,
+                on,
+            } => watch::handle(
+                script_name,
+                patterns,
+                watch::WatchOptions {
+                    ignore,
+                    mode,
+                    poll,
+                    debounce: debounce.map(Duration::from_millis),
+                    on,
+                },
+            ),
+            Commands::Clean {
+                cache,
+                all,
+                global,
+                dry_run,
+                yes,
+                recursive,
+                scan,
+                older_than,
+                all_agents,
+            } => clean::handle(
+                cache,
+                all,
+                global,
+                scan.map(|dir| clean::ScanOptions { dir, older_than }),
+                clean::CleanOptions {
+                    dry_run,
+                    yes,
+                    recursive,
+                    all_agents,
+                },
+            ),
             Commands::View { package, version } => view::handle(package, version),
+            Commands::Completions { shell } => completions::handle(shell),
+            Commands::Complete { kind } => completions::handle_complete(&kind),
             Commands::Help => {
                 StyledOutput::print_help();
                 Ok(())
@@ -0,0 +1,17 @@
+use anyhow::Result;
+
+use crate::completions::{self, Shell};
+
+pub fn handle(shell: Shell) -> Result<()> {
+    print!("{}", completions::script(shell));
+    Ok(())
+}
+
+/// Print the values of `kind` one per line; nothing when there are none
+pub fn handle_complete(kind: &str) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    for value in completions::dynamic_values(kind, &cwd) {
+        println!("{}", value);
+    }
+    Ok(())
+}
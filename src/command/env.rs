@@ -0,0 +1,76 @@
+use crate::display::StyledOutput;
+use crate::dotenv::{self, is_secret, EnvOptions, EnvVar, MASK};
+use crate::utils::find_up;
+use anyhow::Result;
+use console::style;
+use std::path::Path;
+
+pub fn handle(options: &EnvOptions, all: bool) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let dir = find_up("package.json", &cwd)
+        .and_then(|path| path.parent().map(Path::to_path_buf))
+        .unwrap_or(cwd);
+    let vars = dotenv::load(&dir, options)?;
+
+    let mode = options
+        .mode()
+        .map(|mode| format!("mode {}", mode))
+        .unwrap_or_else(|| "no mode".to_string());
+    println!();
+    StyledOutput::titled(&format!(
+        "Environment {}",
+        style(format!("({})", mode)).dim()
+    ));
+    println!();
+
+    if options.disabled {
+        StyledOutput::body(&style(".env loading disabled").dim().to_string());
+    } else if vars.is_empty() {
+        StyledOutput::body(
+            &style("No variables loaded from .env files")
+                .dim()
+                .to_string(),
+        );
+    }
+
+    // Group by the file each variable came from, in precedence order
+    let mut sources: Vec<&Path> = Vec::new();
+    for var in &vars {
+        if !sources.contains(&var.source.as_path()) {
+            sources.push(&var.source);
+        }
+    }
+    for source in sources {
+        let label = source.strip_prefix(&dir).unwrap_or(source);
+        println!("  {}", style(label.display()).bold());
+        let group: Vec<&EnvVar> = vars.iter().filter(|v| v.source == source).collect();
+        for (i, var) in group.iter().enumerate() {
+            StyledOutput::tree_item(&entry(&var.key, &var.value), i == group.len() - 1);
+        }
+    }
+
+    if all {
+        println!();
+        println!("  {}", style("process").bold());
+        let mut process: Vec<(String, String)> = std::env::vars().collect();
+        process.sort();
+        for (i, (key, value)) in process.iter().enumerate() {
+            StyledOutput::tree_item(&entry(key, value), i == process.len() - 1);
+        }
+    } else {
+        println!();
+        StyledOutput::hint("kn env --all to include the process environment");
+    }
+
+    println!();
+    Ok(())
+}
+
+fn entry(key: &str, value: &str) -> String {
+    let value = if is_secret(key, value) {
+        style(MASK).dim().to_string()
+    } else {
+        value.to_string()
+    };
+    format!("{}={}", style(key).cyan(), value)
+}
@@ -1,10 +1,26 @@
+use crate::dotenv::{self, EnvOptions};
 use crate::parse::parse_nlx;
-use crate::runner::run_cli;
+use crate::runner::{run_cli, DetectOptions};
+use crate::utils::find_up;
 use anyhow::Result;
 
-pub fn handle(command: String, args: Vec<String>) -> Result<()> {
+pub fn handle(command: String, args: Vec<String>, env_options: EnvOptions) -> Result<()> {
     let mut full_args = vec![command];
     full_args.extend(args);
 
-    run_cli(parse_nlx, None, full_args)
+    // `.env` files are read from the package root, like for scripts
+    let cwd = std::env::current_dir()?;
+    let package_dir = find_up("package.json", &cwd)
+        .and_then(|path| path.parent().map(|dir| dir.to_path_buf()))
+        .unwrap_or(cwd);
+    let env = dotenv::load(&package_dir, &env_options)?
+        .into_iter()
+        .map(|var| (var.key, var.value))
+        .collect();
+
+    run_cli(
+        parse_nlx,
+        Some(DetectOptions::new().with_env(env)),
+        full_args,
+    )
 }
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use console::style;
+
+use crate::display::StyledOutput;
+use crate::history::{script_stats, time_ago, History, HistoryEntry};
+use crate::utils::find_and_parse_package_json;
+
+pub fn handle(all: bool, limit: usize, json: bool) -> Result<()> {
+    let history = match History::open() {
+        Some(history) => history,
+        None => anyhow::bail!("Could not determine the data directory for run history"),
+    };
+
+    let entries: Vec<HistoryEntry> = if all {
+        history.entries()
+    } else {
+        let cwd = std::env::current_dir()?;
+        let (path, _package) = find_and_parse_package_json(&cwd)?;
+        let project = path.parent().map(|p| p.to_path_buf()).unwrap_or(cwd);
+        history.project_entries(&project)
+    };
+
+    let recent: Vec<&HistoryEntry> = entries.iter().rev().take(limit).collect();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&recent)?);
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        StyledOutput::info("No script runs recorded yet");
+        StyledOutput::hint("Runs of kn run <script> show up here");
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = recent
+        .iter()
+        .map(|entry| {
+            let icon = if entry.exit_code == 0 {
+                style("✔").green()
+            } else {
+                style("✖").red()
+            };
+            let mut row = vec![
+                format!("{} {}", icon, entry.command()),
+                StyledOutput::duration(entry.duration_ms as f64 / 1000.0),
+                entry.exit_code.to_string(),
+                style(time_ago(entry.timestamp)).dim().to_string(),
+            ];
+            if all {
+                row.push(style(entry.project.display()).dim().to_string());
+            }
+            row
+        })
+        .collect();
+
+    let mut headers = vec!["Script", "Duration", "Exit", "When"];
+    if all {
+        headers.push("Project");
+    }
+
+    println!();
+    StyledOutput::titled("Recent runs");
+    println!();
+    StyledOutput::table(&headers, &rows);
+
+    let stats_rows: Vec<Vec<String>> = script_stats(&entries)
+        .iter()
+        .map(|stats| {
+            let failures = if stats.failures > 0 {
+                style(stats.failures.to_string()).red().to_string()
+            } else {
+                style("0").dim().to_string()
+            };
+            vec![
+                stats.script.clone(),
+                stats.runs.to_string(),
+                StyledOutput::duration(stats.average_ms as f64 / 1000.0),
+                StyledOutput::duration(stats.last_ms as f64 / 1000.0),
+                failures,
+            ]
+        })
+        .collect();
+
+    println!();
+    StyledOutput::titled("Timings");
+    println!();
+    StyledOutput::table(
+        &["Script", "Runs", "Average", "Last", "Failed"],
+        &stats_rows,
+    );
+    println!();
+
+    Ok(())
+}
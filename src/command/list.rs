@@ -13,8 +13,7 @@ pub fn handle(json: bool) -> Result<()> {
         }
         Err(e) => {
             drop(spinner);
-            StyledOutput::error(&e.to_string());
-            std::process::exit(1);
+            return Err(e);
         }
     };
 
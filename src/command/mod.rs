@@ -1,7 +1,11 @@
+pub mod cache;
 pub mod clean;
 pub mod clean_install;
 pub mod cli;
+pub mod completions;
+pub mod env;
 pub mod execute;
+pub mod history;
 pub mod info;
 pub mod install;
 pub mod list;
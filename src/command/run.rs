@@ -1,88 +1,700 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use console::style;
-use inquire::Select;
+use indexmap::IndexMap;
+use inquire::Confirm;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tracing::debug;
 
-use crate::command_utils::run_script_fast;
+use crate::agents::Agent;
+use crate::cache::{self, Cache, CacheConfig, CacheEntry};
+use crate::command_utils::{package_script_steps, ScriptPackage};
+use crate::config::get_logs_dir;
+use crate::detect::detect;
 use crate::display::StyledOutput;
-use crate::utils::{find_and_parse_package_json, levenshtein_distance};
+use crate::dotenv::{self, EnvOptions};
+use crate::error::{exit_code, KnError};
+use crate::history::{rank_scripts, time_ago, History, HistoryEntry};
+use crate::multi_run::{
+    first_failure, print_summary, run_tasks, MultiRunOptions, Task, TaskStatus,
+};
+use crate::picker::{self, PickerItem};
+use crate::run_log::{self, LogHeader, RunLog};
+use crate::runner::DetectOptions;
+use crate::script_pattern::{expand, is_pattern};
+use crate::script_runner::{quote_arg, run_steps, run_steps_captured, OutputLog, ScriptStep};
+use crate::utils::{is_interactive, levenshtein_distance};
+use crate::workspace;
+
+/// Options of `kn run` besides the scripts and their arguments
+#[derive(Debug, Clone, Default)]
+pub struct RunOptions {
+    /// Exit quietly when the script does not exist
+    pub if_present: bool,
+    /// Run the workspace root's script instead of the nearest package's
+    pub root: bool,
+    /// Run cached scripts even when their inputs are unchanged
+    pub force: bool,
+    /// Also write the output to this file
+    pub log: Option<PathBuf>,
+    pub env: EnvOptions,
+}
+
+/// Run a script of the nearest package, or with `--root` of the workspace root
+pub fn handle(script_name: Option<String>, args: Vec<String>, options: &RunOptions) -> Result<()> {
+    let package = match find_package(options.root) {
+        Ok(package) => package,
+        Err(_) if options.if_present => return Ok(()),
+        Err(e) => return Err(e),
+    };
 
-pub fn handle(script_name: Option<String>, args: Vec<String>, _if_present: bool) -> Result<()> {
     match script_name {
+        Some(script) if is_pattern(&script) => {
+            run_many(
+                &package,
+                vec![script],
+                MultiRunOptions::default(),
+                &args,
+                options,
+            )?;
+        }
         Some(script) => {
-            // Try fuzzy match if script not found
-            let final_script = if script_exists(&script) {
-                script
-            } else {
-                match fuzzy_find_script(&script)? {
-                    Some(found) => {
-                        println!(
-                            "  {} Did you mean {}? Running it...\n",
-                            style("›").dim(),
-                            style(&found).cyan(),
-                        );
-                        found
-                    }
-                    None => script,
-                }
+            let final_script = match resolve_script(&package, &script, options.if_present)? {
+                Some(name) => name,
+                None => return Ok(()),
             };
 
-            let start = std::time::Instant::now();
-            let result = run_script_fast(&final_script, &args);
-            let duration = start.elapsed();
+            run_and_record(&package, &final_script, &args, options)?;
+        }
+        None => {
+            show_available_scripts(&package, options)?;
+        }
+    }
+    Ok(())
+}
+
+fn find_package(root: bool) -> Result<ScriptPackage> {
+    let cwd = std::env::current_dir()?;
+    let package = ScriptPackage::find(&cwd, root)?;
+    debug!(
+        "Running scripts of {} with {}",
+        package.manifest.display(),
+        package.agent
+    );
+    Ok(package)
+}
+
+/// Rerun the last script run in this project, with the same arguments. Runs
+/// of the workspace root's scripts with `--root` count too.
+pub fn handle_last() -> Result<()> {
+    let project = find_package(false)?.dir().to_path_buf();
+    let root = find_package(true)?.dir().to_path_buf();
+    let last = History::open()
+        .and_then(|history| {
+            history
+                .entries()
+                .into_iter()
+                .rfind(|e| &e.project == if e.root { &root } else { &project })
+        })
+        .with_context(|| {
+            format!(
+                "No previous run in this project\n    {} {}",
+                style("└").dim(),
+                style("Run a script with kn run <script> first").dim(),
+            )
+        })?;
 
-            if result.is_ok() {
-                StyledOutput::completion(duration.as_secs_f64());
-            }
+    StyledOutput::info(&format!("Rerunning {}", style(last.command()).cyan()));
+    println!();
+    let options = RunOptions {
+        root: last.root,
+        env: last.env,
+        ..Default::default()
+    };
+    handle(Some(last.script), last.args, &options)
+}
+
+/// `env` with `--env-file` paths made absolute, to replay from anywhere
+fn replayable_env(env: &EnvOptions) -> EnvOptions {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    EnvOptions {
+        files: env.files.iter().map(|file| cwd.join(file)).collect(),
+        ..env.clone()
+    }
+}
+
+/// Run one script, print its duration and store the run in the history
+fn run_and_record(
+    package: &ScriptPackage,
+    script: &str,
+    args: &[String],
+    options: &RunOptions,
+) -> Result<()> {
+    let started = SystemTime::now();
+    let start = std::time::Instant::now();
+    let log_file = log_path(options, package.dir(), script, started)
+        .and_then(|path| open_log(&path, package.agent, started));
+    let result = match cache::script_config(&package.manifest, script) {
+        Ok(Some(config)) => run_cached(package, script, args, &config, options, log_file.as_ref()),
+        Ok(None) => package
+            .script_steps(script, args, &options.env)
+            .and_then(|steps| run_logged(steps, log_file.as_ref())),
+        Err(e) => Err(e),
+    };
+    let duration = start.elapsed();
+
+    if result.is_ok() {
+        StyledOutput::completion(duration.as_secs_f64());
+    }
+
+    let exit_code = match &result {
+        Ok(()) => 0,
+        Err(e) => exit_code(e),
+    };
+    if let Some(log_file) = &log_file {
+        finish_log(log_file, duration, exit_code);
+    }
+    record_run(package.dir(), script, args, duration, exit_code, options);
+
+    result
+}
+
+/// Store a finished run of `script` in the history
+fn record_run(
+    project: &Path,
+    script: &str,
+    args: &[String],
+    duration: Duration,
+    exit_code: i32,
+    options: &RunOptions,
+) {
+    let Some(history) = History::open() else {
+        return;
+    };
+    let entry = HistoryEntry {
+        env: replayable_env(&options.env),
+        root: options.root,
+        ..HistoryEntry::new(project, script, args, duration, exit_code)
+    };
+    if let Err(e) = history.record(&entry) {
+        debug!("Failed to record run history: {}", e);
+    }
+}
 
-            result?;
+/// Where to log a run: `--log`, else a new file in the `logs_dir` config
+fn log_path(options: &RunOptions, base: &Path, name: &str, started: SystemTime) -> Option<PathBuf> {
+    run_log::log_path(
+        options.log.as_deref(),
+        get_logs_dir().as_deref(),
+        base,
+        name,
+        started,
+    )
+}
+
+/// Create the log file of a run and write its header; failing to do so
+/// does not fail the run
+fn open_log(path: &Path, agent: Agent, started: SystemTime) -> Option<Arc<Mutex<RunLog>>> {
+    let command = std::iter::once("kn".to_string())
+        .chain(std::env::args().skip(1).map(|arg| quote_arg(&arg)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let header = LogHeader {
+        command,
+        agent: agent.to_string(),
+        cwd: std::env::current_dir().unwrap_or_default(),
+        started,
+    };
+    match RunLog::create(path, &header) {
+        Ok(log) => Some(Arc::new(Mutex::new(log))),
+        Err(e) => {
+            StyledOutput::warning(&format!("Failed to save log: {}", e));
+            None
         }
-        None => {
-            show_available_scripts()?;
+    }
+}
+
+/// End the log file of a run with its duration and exit code
+fn finish_log(log_file: &Mutex<RunLog>, duration: Duration, exit_code: i32) {
+    let mut log_file = log_file.lock().unwrap_or_else(|e| e.into_inner());
+    match log_file.finish(duration, exit_code) {
+        Ok(()) => StyledOutput::hint(&format!("Log saved to {}", log_file.path().display())),
+        Err(e) => StyledOutput::warning(&format!("Failed to save log: {}", e)),
+    }
+}
+
+/// Run steps, also writing their output to `log_file` when there is one
+fn run_logged(steps: Vec<ScriptStep>, log_file: Option<&Arc<Mutex<RunLog>>>) -> Result<()> {
+    match log_file {
+        Some(log_file) => run_steps_captured(steps, &[log_file.clone() as OutputLog]),
+        None => run_steps(steps),
+    }
+}
+
+/// Replay a previous run of a cached script when its inputs are unchanged,
+/// otherwise run it and store its outputs and log. The output, replayed or
+/// not, is also written to `log_file`.
+fn run_cached(
+    package: &ScriptPackage,
+    script: &str,
+    args: &[String],
+    config: &CacheConfig,
+    options: &RunOptions,
+    log_file: Option<&Arc<Mutex<RunLog>>>,
+) -> Result<()> {
+    let Some(cache) = Cache::open() else {
+        return package
+            .script_steps(script, args, &options.env)
+            .and_then(|steps| run_logged(steps, log_file));
+    };
+
+    // Configured variables count whether they come from the shell or .env files
+    let loaded = dotenv::load(package.dir(), &options.env)?;
+    let env: Vec<(String, String)> = config
+        .env
+        .iter()
+        .map(|key| {
+            let value = std::env::var(key)
+                .ok()
+                .or_else(|| {
+                    loaded
+                        .iter()
+                        .find(|v| &v.key == key)
+                        .map(|v| v.value.clone())
+                })
+                .unwrap_or_default();
+            (key.clone(), value)
+        })
+        .collect();
+    let commands: Vec<String> = [
+        format!("pre{}", script),
+        script.to_string(),
+        format!("post{}", script),
+    ]
+    .iter()
+    .filter_map(|name| package.scripts.get(name).cloned())
+    .collect();
+    let hash = cache::compute_hash(package.dir(), script, &commands, args, config, &env)?;
+    debug!("Cache hash of {}: {}", script, hash);
+
+    if let Some(entry) = cache.lookup(&hash).filter(|_| !options.force) {
+        let log = cache.restore(&entry, &config.outputs)?;
+        if let Some(log_file) = log_file {
+            let _ = log_file
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .write_all(&log);
         }
+        println!(
+            "  {} {}  {}",
+            style("▸").cyan(),
+            style(script).bold(),
+            style(format!(
+                "cached {}, replaying output",
+                time_ago(entry.timestamp)
+            ))
+            .dim(),
+        );
+        println!();
+        io::stdout().write_all(&log)?;
+        println!();
+        StyledOutput::hint(&format!(
+            "Restored {} output {} · saved {}",
+            entry.outputs.len(),
+            if entry.outputs.len() == 1 {
+                "file"
+            } else {
+                "files"
+            },
+            StyledOutput::duration(entry.duration_ms as f64 / 1000.0),
+        ));
+        return Ok(());
+    }
+
+    let start = std::time::Instant::now();
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let mut logs: Vec<OutputLog> = vec![captured.clone()];
+    logs.extend(log_file.map(|log_file| log_file.clone() as OutputLog));
+    package
+        .script_steps(script, args, &options.env)
+        .and_then(|steps| run_steps_captured(steps, &logs))?;
+    let log = std::mem::take(&mut *captured.lock().unwrap_or_else(|e| e.into_inner()));
+
+    let entry = CacheEntry {
+        hash,
+        project: package.dir().to_path_buf(),
+        script: script.to_string(),
+        duration_ms: start.elapsed().as_millis() as u64,
+        timestamp: cache::now(),
+        outputs: Vec::new(),
+    };
+    if let Err(e) = cache.store(entry, &config.outputs, &log) {
+        StyledOutput::warning(&format!("Failed to cache {}: {}", script, e));
     }
     Ok(())
 }
 
-fn script_exists(script_name: &str) -> bool {
-    let cwd = match std::env::current_dir() {
-        Ok(d) => d,
-        Err(_) => return false,
+/// Run several scripts, sequentially or in parallel, and print a summary;
+/// `args` are passed to every script
+pub fn handle_many(
+    scripts: Vec<String>,
+    args: &[String],
+    multi: MultiRunOptions,
+    options: &RunOptions,
+) -> Result<()> {
+    let package = match find_package(options.root) {
+        Ok(package) => package,
+        Err(_) if options.if_present => return Ok(()),
+        Err(e) => return Err(e),
     };
-    if let Ok((_path, package)) = find_and_parse_package_json(&cwd) {
-        if let Some(scripts) = package.scripts {
-            return scripts.contains_key(script_name);
+    run_many(&package, scripts, multi, args, options)
+}
+
+/// Run scripts given by name or pattern; `args` are passed to every script
+fn run_many(
+    package: &ScriptPackage,
+    requested: Vec<String>,
+    multi: MultiRunOptions,
+    args: &[String],
+    options: &RunOptions,
+) -> Result<()> {
+    let available = &package.scripts;
+
+    let mut names: Vec<String> = Vec::new();
+    let mut expansions = Vec::new();
+    for entry in requested {
+        let matched = if is_pattern(&entry) {
+            expand(&entry, available)
+        } else if available.contains_key(&entry) {
+            vec![entry.clone()]
+        } else {
+            Vec::new()
+        };
+
+        if matched.is_empty() {
+            if options.if_present {
+                debug!("No scripts for '{}', skipping", entry);
+                continue;
+            }
+            let message = if is_pattern(&entry) {
+                format!("No scripts match '{}'", entry)
+            } else {
+                format!("Script '{}' not found in package.json", entry)
+            };
+            bail!(
+                "{}\n    {} {}",
+                message,
+                style("└").dim(),
+                style("Run kn ls to see available scripts").dim(),
+            );
         }
+
+        if is_pattern(&entry) {
+            expansions.push((entry, matched.clone()));
+        }
+        for name in matched {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+
+    if names.is_empty() {
+        return Ok(());
     }
-    false
+
+    let mode = if multi.parallel {
+        "in parallel"
+    } else {
+        "in sequence"
+    };
+    println!();
+    StyledOutput::titled(&format!(
+        "Running {} scripts {}",
+        names.len(),
+        style(mode).dim()
+    ));
+    for (pattern, matched) in &expansions {
+        StyledOutput::hint(&format!("{} → {}", pattern, matched.join(", ")));
+    }
+    for (i, name) in names.iter().enumerate() {
+        StyledOutput::tree_item(&format!("{}", style(name).cyan()), i == names.len() - 1);
+    }
+    println!();
+
+    let log_name = names.join("+");
+    let runs = names
+        .iter()
+        .map(|name| (package.dir().to_path_buf(), name.clone()))
+        .collect();
+    let tasks = names
+        .into_iter()
+        .map(|name| {
+            Ok(Task {
+                steps: package.script_steps(&name, args, &options.env)?,
+                label: name,
+                depends_on: Vec::new(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let log = LogTarget {
+        options,
+        base: package.dir(),
+        name: &log_name,
+        agent: package.agent,
+    };
+    run_all(tasks, multi, "Script", log, runs, args)
+}
+
+/// Where and how the log of a multi-task run is written
+struct LogTarget<'a> {
+    options: &'a RunOptions,
+    /// Directory a relative `logs_dir` is resolved against
+    base: &'a Path,
+    name: &'a str,
+    agent: Agent,
+}
+
+/// Run tasks, print their summary and write the log when one is wanted.
+/// `runs` holds the package directory and script of each task, recorded in
+/// the history once the task has run.
+fn run_all(
+    tasks: Vec<Task>,
+    mut multi: MultiRunOptions,
+    label_header: &str,
+    log: LogTarget,
+    runs: Vec<(PathBuf, String)>,
+    args: &[String],
+) -> Result<()> {
+    let started = SystemTime::now();
+    let start = std::time::Instant::now();
+    let log_file = log_path(log.options, log.base, log.name, started)
+        .and_then(|path| open_log(&path, log.agent, started));
+    if let Some(log_file) = &log_file {
+        multi.log = Some(log_file.clone());
+    }
+
+    let results = run_tasks(tasks, &multi);
+    print_summary(label_header, &results);
+
+    for ((project, script), result) in runs.iter().zip(&results) {
+        let exit_code = match result.status {
+            TaskStatus::Passed => 0,
+            TaskStatus::Failed(code) => code,
+            // Never ran to completion
+            TaskStatus::Cancelled | TaskStatus::Skipped => continue,
+        };
+        let duration = result.duration.unwrap_or_default();
+        record_run(project, script, args, duration, exit_code, log.options);
+    }
+
+    let failure = first_failure(&results);
+    if let Some(log_file) = &log_file {
+        finish_log(log_file, start.elapsed(), failure.unwrap_or(0));
+    }
+
+    match failure {
+        Some(code) => Err(KnError::Exit(code).into()),
+        None => Ok(()),
+    }
+}
+
+/// Which workspace packages `kn run --recursive` runs in
+#[derive(Debug, Clone, Default)]
+pub struct WorkspaceSelection {
+    pub filters: Vec<String>,
+    /// Only packages with changes since this git ref
+    pub since: Option<String>,
 }
 
-fn fuzzy_find_script(input: &str) -> Result<Option<String>> {
+/// Run a script in every workspace package that defines it, dependencies first
+pub fn handle_recursive(
+    script_name: &str,
+    args: &[String],
+    selection: &WorkspaceSelection,
+    multi: MultiRunOptions,
+    options: &RunOptions,
+) -> Result<()> {
     let cwd = std::env::current_dir()?;
-    let (_path, package) = find_and_parse_package_json(&cwd)?;
-    let scripts = package.scripts.unwrap_or_default();
+    let Some(root) = workspace::find_root(&cwd) else {
+        bail!(
+            "No workspace found\n    {} {}",
+            style("└").dim(),
+            style("Add a workspaces field to package.json or a pnpm-workspace.yaml").dim(),
+        );
+    };
+
+    let all = workspace::discover(&root)?;
+    let mut selected = workspace::apply_filters(all.clone(), &selection.filters, &root);
+    if let Some(git_ref) = &selection.since {
+        let changed = workspace::changed_since(&root, git_ref)?;
+        selected = workspace::filter_changed(selected, &changed);
+    }
+    selected.retain(|p| p.scripts.contains_key(script_name));
+
+    if selected.is_empty() {
+        if options.if_present || selection.since.is_some() {
+            StyledOutput::info(&format!(
+                "No {}packages define {}",
+                if selection.since.is_some() {
+                    "changed "
+                } else {
+                    ""
+                },
+                style(script_name).cyan()
+            ));
+            return Ok(());
+        }
+        bail!("No workspace packages define '{}'", script_name);
+    }
+
+    let agent = detect(DetectOptions {
+        cwd: root.clone(),
+        ..Default::default()
+    })
+    .unwrap_or(Agent::Npm);
+
+    let ordered = workspace::topological_order(&all, selected)?;
+
+    let mode = if multi.parallel {
+        "in parallel"
+    } else {
+        "in sequence"
+    };
+    println!();
+    StyledOutput::titled(&format!(
+        "Running {} in {} {} {}",
+        style(script_name).cyan(),
+        ordered.len(),
+        if ordered.len() == 1 {
+            "package"
+        } else {
+            "packages"
+        },
+        style(mode).dim()
+    ));
+    for (i, (package, _)) in ordered.iter().enumerate() {
+        let relative = package.dir.strip_prefix(&root).unwrap_or(&package.dir);
+        StyledOutput::tree_item(
+            &format!(
+                "{}  {}",
+                style(&package.name).cyan(),
+                style(relative.display()).dim()
+            ),
+            i == ordered.len() - 1,
+        );
+    }
+    println!();
 
-    let mut matches: Vec<(String, usize)> = scripts
+    let runs = ordered
+        .iter()
+        .map(|(package, _)| (package.dir.clone(), script_name.to_string()))
+        .collect();
+    let tasks = ordered
+        .into_iter()
+        .map(|(package, depends_on)| {
+            Ok(Task {
+                steps: package_script_steps(
+                    &package.manifest_path(),
+                    script_name,
+                    args,
+                    agent,
+                    &options.env,
+                )?,
+                label: package.name,
+                depends_on,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let log = LogTarget {
+        options,
+        base: &root,
+        name: script_name,
+        agent,
+    };
+    run_all(tasks, multi, "Package", log, runs, args)
+}
+
+/// How a requested script name matched the package's scripts
+#[derive(Debug, PartialEq, Eq)]
+enum Resolution {
+    Exact,
+    Suggestion(String),
+    Missing,
+}
+
+fn match_script(input: &str, scripts: &IndexMap<String, String>) -> Resolution {
+    if scripts.contains_key(input) {
+        return Resolution::Exact;
+    }
+
+    let mut matches: Vec<(&String, usize)> = scripts
         .keys()
-        .map(|name| (name.clone(), levenshtein_distance(input, name)))
+        .map(|name| (name, levenshtein_distance(input, name)))
         .filter(|(_, dist)| *dist <= 2)
         .collect();
 
     matches.sort_by_key(|(_, dist)| *dist);
 
-    Ok(matches.first().map(|(name, _)| name.clone()))
+    match matches.first() {
+        Some((name, _)) => Resolution::Suggestion((*name).clone()),
+        None => Resolution::Missing,
+    }
 }
 
-fn show_available_scripts() -> Result<()> {
-    let cwd = std::env::current_dir()?;
-    let (_path, package) = match find_and_parse_package_json(&cwd) {
-        Ok(result) => result,
-        Err(_) => {
-            StyledOutput::error("No package.json found");
-            std::process::exit(1);
+/// Resolve the script to run. Returns `None` when it is missing and
+/// `--if-present` was given. A fuzzy match is only run after the user
+/// confirms it, so a typo never silently runs a different script.
+fn resolve_script(
+    package: &ScriptPackage,
+    script: &str,
+    if_present: bool,
+) -> Result<Option<String>> {
+    let not_found = format!("Script '{}' not found in package.json", script);
+    match match_script(script, &package.scripts) {
+        Resolution::Exact => Ok(Some(script.to_string())),
+        _ if if_present => {
+            debug!("Script '{}' not present, skipping", script);
+            Ok(None)
         }
-    };
+        Resolution::Suggestion(found) if is_interactive() => {
+            let confirmed = Confirm::new(&format!(
+                "Script {} not found. Run {} instead?",
+                style(script).yellow(),
+                style(&found).cyan(),
+            ))
+            .with_default(true)
+            .prompt()
+            .unwrap_or(false);
+
+            if confirmed {
+                println!();
+                Ok(Some(found))
+            } else {
+                bail!(not_found)
+            }
+        }
+        Resolution::Suggestion(found) => bail!(
+            "{}\n    {} {}",
+            not_found,
+            style("└").dim(),
+            style(format!("Did you mean {}?", found)).dim(),
+        ),
+        Resolution::Missing => bail!(
+            "{}\n    {} {}",
+            not_found,
+            style("└").dim(),
+            style("Run kn ls to see available scripts").dim(),
+        ),
+    }
+}
 
-    let scripts = package.scripts.unwrap_or_default();
+fn show_available_scripts(package: &ScriptPackage, options: &RunOptions) -> Result<()> {
+    let scripts = &package.scripts;
 
     if scripts.is_empty() {
         StyledOutput::info("No scripts found in this package");
@@ -90,53 +702,69 @@ fn show_available_scripts() -> Result<()> {
         return Ok(());
     }
 
-    // Build interactive selection
-    let max_name = scripts.keys().map(|k| k.len()).max().unwrap_or(10).min(24);
+    // Recently and frequently run scripts come first
+    let entries = History::open()
+        .map(|history| history.project_entries(package.dir()))
+        .unwrap_or_default();
+    let script_names = rank_scripts(&entries, scripts);
 
-    let options: Vec<String> = scripts
+    if !is_interactive() {
+        bail!(
+            "No script specified\n    {} {}",
+            style("└").dim(),
+            style("Run kn run <script>, or kn ls to see available scripts").dim(),
+        );
+    }
+
+    let items: Vec<PickerItem> = script_names
         .iter()
-        .map(|(name, cmd)| {
-            let cmd_preview = if cmd.len() > 40 {
-                format!("{}…", &cmd[..39])
-            } else {
-                cmd.clone()
-            };
-            format!(
-                "{:<width$}  {}",
-                name,
-                style(&cmd_preview).dim(),
-                width = max_name
-            )
+        .map(|name| PickerItem {
+            name: name.clone(),
+            detail: scripts[name].clone(),
         })
         .collect();
 
-    let script_names: Vec<String> = scripts.keys().cloned().collect();
-
     println!();
-    match Select::new("Select a script to run:", options).prompt() {
-        Ok(selection) => {
-            if let Some(script_name) = script_names
-                .iter()
-                .find(|name| selection.starts_with(name.as_str()))
-            {
-                println!();
+    let Some(picked) = picker::pick("Run script:", &items)? else {
+        StyledOutput::dim("Cancelled");
+        return Ok(());
+    };
 
-                let start = std::time::Instant::now();
-                let result = run_script_fast(script_name, &[]);
-                let duration = start.elapsed();
+    let names: Vec<String> = picked
+        .into_iter()
+        .map(|i| script_names[i].clone())
+        .collect();
+    if let [name] = names.as_slice() {
+        run_and_record(package, name, &[], options)
+    } else {
+        run_many(package, names, MultiRunOptions::default(), &[], options)
+    }
+}
 
-                if result.is_ok() {
-                    StyledOutput::completion(duration.as_secs_f64());
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                result?;
-            }
-        }
-        Err(_) => {
-            println!();
-            StyledOutput::dim("Cancelled");
-        }
+    fn scripts(names: &[&str]) -> IndexMap<String, String> {
+        names
+            .iter()
+            .map(|name| (name.to_string(), format!("echo {}", name)))
+            .collect()
     }
 
-    Ok(())
+    #[test]
+    fn test_match_script() {
+        let scripts = scripts(&["build", "build:prod", "test", "lint"]);
+
+        assert_eq!(match_script("build", &scripts), Resolution::Exact);
+        assert_eq!(
+            match_script("tset", &scripts),
+            Resolution::Suggestion("test".to_string())
+        );
+        assert_eq!(
+            match_script("biuld", &scripts),
+            Resolution::Suggestion("build".to_string())
+        );
+        assert_eq!(match_script("deploy", &scripts), Resolution::Missing);
+    }
 }
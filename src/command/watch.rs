@@ -1,190 +1,514 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use console::style;
-use std::collections::HashMap;
-use std::path::{Path, PathBuf};
-use std::time::{Duration, SystemTime};
+use std::io::{self, IsTerminal, Read};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
 
-use crate::command_utils::run_script_fast;
+use crate::cache;
+use crate::child;
+use crate::cli_parser::WatchMode;
+use crate::command_utils::ScriptPackage;
 use crate::display::StyledOutput;
+use crate::dotenv::EnvOptions;
+use crate::error::{exit_code, find_kn_error, KnError};
+use crate::glob::{relative_path, GlobSet};
+use crate::script_runner::{spawn_steps, RunningSteps};
+use crate::watcher::{watch_config, Change, WatchConfig, WatchRule, Watcher, DEFAULT_DEBOUNCE};
 
-pub fn handle(script_name: String, patterns: Vec<String>) -> Result<()> {
-    let watch_patterns = if patterns.is_empty() {
-        vec![
-            "src/**/*".to_string(),
-            "*.js".to_string(),
-            "*.ts".to_string(),
-        ]
-    } else {
-        patterns
+/// How often key presses, file events and finished runs are checked
+const TICK: Duration = Duration::from_millis(50);
+/// Changed files listed before the rest are summarised
+const SHOWN_CHANGES: usize = 10;
+/// Directories scripts usually write to; watching them would rerun the
+/// script every time it writes
+const OUTPUT_DIRS: [&str; 9] = [
+    "dist",
+    "build",
+    "out",
+    "coverage",
+    "target",
+    ".next",
+    ".nuxt",
+    ".svelte-kit",
+    ".turbo",
+];
+
+/// Options of `kn watch` besides the script and patterns
+#[derive(Debug, Default)]
+pub struct WatchOptions {
+    /// Patterns of files whose changes are ignored
+    pub ignore: Vec<String>,
+    pub mode: WatchMode,
+    /// Walk the tree instead of using native file events
+    pub poll: bool,
+    /// Quiet period before changes are acted on
+    pub debounce: Option<Duration>,
+    /// Scripts to run for changes to other patterns
+    pub on: Vec<WatchRule>,
+}
+
+/// A watched script and its current run
+struct Job {
+    rule: WatchRule,
+    globs: GlobSet,
+    run: Option<RunningSteps>,
+    started: Instant,
+    /// Changed files of a run waiting for the current one to finish
+    queued: Option<Vec<String>>,
+}
+
+pub fn handle(
+    script_name: Option<String>,
+    patterns: Vec<String>,
+    options: WatchOptions,
+) -> Result<()> {
+    let WatchOptions {
+        mut ignore,
+        mode,
+        poll,
+        debounce,
+        on,
+    } = options;
+
+    // Patterns are relative to the package root, which is also the watched
+    // directory; outside a package, to the current directory
+    let current_dir = std::env::current_dir()?;
+    let package = ScriptPackage::find(&current_dir, false).ok();
+    let root = package
+        .as_ref()
+        .map_or(current_dir, |package| package.dir().to_path_buf());
+    let config = match &package {
+        Some(package) => watch_config(&package.manifest)?,
+        None => WatchConfig::default(),
     };
+    ignore.extend(config.ignore.iter().cloned());
+
+    // The script given on the command line and --on rules, or else the
+    // package's kn.watch map
+    let mut rules = Vec::new();
+    if let Some(script) = script_name {
+        let patterns = if patterns.is_empty() {
+            vec![
+                "src/**/*".to_string(),
+                "*.js".to_string(),
+                "*.ts".to_string(),
+            ]
+        } else {
+            patterns
+        };
+        rules.push(WatchRule {
+            patterns,
+            script,
+            args: Vec::new(),
+        });
+    }
+    rules.extend(on);
+    if rules.is_empty() {
+        rules = config.rules();
+    }
+    if rules.is_empty() {
+        bail!(
+            "Watch command requires a script name\n    {} {}",
+            style("└").dim(),
+            style("Pass a script, map patterns with --on <glob>=<script>, or add a kn.watch section to package.json").dim(),
+        );
+    }
+    if let Some(rule) = rules.iter().find(|rule| rule.script.is_empty()) {
+        bail!("No script to run for {}", rule.patterns.join(" "));
+    }
+
+    let mut cache_outputs = Vec::new();
+    if let Some(package) = &package {
+        for rule in &rules {
+            if let Some(config) = cache::script_config(&package.manifest, &rule.script)? {
+                cache_outputs.extend(config.outputs);
+            }
+        }
+    }
+
+    child::catch_interrupts()?;
+    let keys = Keys::start();
 
     // ── Watch header card ──
-    let title = format!("Watch  {}", style(&script_name).cyan(),);
+    let ignored = ignore
+        .iter()
+        .map(|p| format!("!{}", p.trim_start_matches('!')));
+    let (title, shown): (String, Vec<String>) = match rules.as_slice() {
+        [rule] => (
+            style(rule.command()).cyan().to_string(),
+            rule.patterns.iter().cloned().chain(ignored).collect(),
+        ),
+        _ => (
+            format!("{} scripts", rules.len()),
+            rules
+                .iter()
+                .map(|rule| {
+                    format!(
+                        "{} {} {}",
+                        rule.patterns.join(" "),
+                        style("→").dim(),
+                        style(rule.command()).cyan()
+                    )
+                })
+                .chain(ignored)
+                .collect(),
+        ),
+    };
 
     println!();
-    StyledOutput::titled(&title);
-    for (i, pattern) in watch_patterns.iter().enumerate() {
-        let is_last = i == watch_patterns.len() - 1;
+    StyledOutput::titled(&format!("Watch  {}", title));
+    for (i, pattern) in shown.iter().enumerate() {
+        let is_last = i == shown.len() - 1;
         StyledOutput::tree_item(&format!("{}", style(pattern).dim()), is_last);
     }
     println!();
-
-    // Initial run
-    StyledOutput::separator();
-    println!();
-
-    if let Err(e) = run_script_fast(&script_name, &[]) {
-        StyledOutput::error(&format!("Script failed: {}", e));
+    let on_change = match mode {
+        WatchMode::Restart => "Restarts on change",
+        WatchMode::Queue => "Reruns after the current run on change",
+    };
+    if keys.is_some() {
+        StyledOutput::hint(&format!(
+            "{} · press r or Enter to restart · Ctrl+C to stop",
+            on_change
+        ));
+    } else {
+        StyledOutput::hint(&format!("{} · Ctrl+C to stop", on_change));
     }
 
-    // Watch for changes
-    let mut file_times: HashMap<PathBuf, SystemTime> = HashMap::new();
-    let current_dir = std::env::current_dir()?;
-
-    scan_directory_recursive(&current_dir, &watch_patterns, &mut file_times);
-
-    println!();
-    println!(
-        "  {} {}  {}",
-        style("●").cyan().bold(),
-        style("Watching for changes...").cyan(),
-        style("(Ctrl+C to stop)").dim(),
+    // The watcher sees every pattern; each job then picks its own changes
+    let included: Vec<String> = rules
+        .iter()
+        .flat_map(|rule| &rule.patterns)
+        .filter(|pattern| !pattern.starts_with('!'))
+        .cloned()
+        .collect();
+    let mut globs = GlobSet::new(&included);
+    globs.ignore(&ignore);
+    globs.ignore(&output_ignores(&included, cache_outputs));
+    let (mut watcher, fallback) = Watcher::new(
+        root.clone(),
+        globs,
+        poll,
+        debounce.unwrap_or(DEFAULT_DEBOUNCE),
     );
-    println!();
+    if let Some(e) = fallback {
+        StyledOutput::warning(&format!("Falling back to polling: {}", e));
+    }
+
+    // Initial runs
+    let mut jobs: Vec<Job> = rules
+        .into_iter()
+        .map(|rule| Job {
+            globs: GlobSet::new(&rule.patterns),
+            run: start(&rule, &[], keys.is_some()),
+            started: Instant::now(),
+            queued: None,
+            rule,
+        })
+        .collect();
 
     loop {
-        std::thread::sleep(Duration::from_millis(500));
-
-        let mut changed_files = Vec::new();
-        check_changes_recursive(
-            &current_dir,
-            &watch_patterns,
-            &mut file_times,
-            &mut changed_files,
-        );
+        std::thread::sleep(TICK);
 
-        if !changed_files.is_empty() {
-            // Show changed files
-            for (i, path) in changed_files.iter().enumerate() {
-                let relative = path.strip_prefix(&current_dir).unwrap_or(path);
-                let is_last = i == changed_files.len() - 1;
-                let connector = if is_last { "└" } else { "├" };
-                println!(
-                    "  {} {} {}",
-                    style(connector).dim(),
-                    style("~").yellow(),
-                    style(relative.display()).dim(),
-                );
+        if let Some(signal) = child::interrupted() {
+            for job in &mut jobs {
+                if let Some(run) = job.run.take() {
+                    run.stop();
+                }
             }
+            return Err(KnError::Exit(128 + signal).into());
+        }
 
-            println!();
-            StyledOutput::separator();
-            println!();
-
-            if let Err(e) = run_script_fast(&script_name, &[]) {
-                StyledOutput::error(&format!("Script failed: {}", e));
+        let mut finished_any = false;
+        for job in &mut jobs {
+            if !job.run.as_ref().is_some_and(RunningSteps::is_finished) {
+                continue;
+            }
+            if let Some(finished) = job.run.take() {
+                report(&job.rule.script, finished.wait(), job.started.elapsed());
             }
+            if let Some(files) = job.queued.take() {
+                job.run = start(&job.rule, &files, keys.is_some());
+                job.started = Instant::now();
+            }
+            finished_any = true;
+        }
+        if finished_any && jobs.iter().all(|job| job.run.is_none()) {
+            watching();
+        }
+
+        let restart_key = keys.as_ref().is_some_and(Keys::restart_requested);
+        let changes = watcher.changes();
 
-            println!();
+        // The jobs whose patterns match a change, with the files to pass on
+        let triggered: Vec<(usize, Vec<String>)> = jobs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, job)| {
+                let matched: Vec<&Change> = changes
+                    .iter()
+                    .filter(|change| job.matches(change, &root))
+                    .collect();
+                (restart_key || !matched.is_empty()).then(|| (i, changed_files(&matched, &root)))
+            })
+            .collect();
+        if triggered.is_empty() {
+            continue;
+        }
+
+        println!();
+        // Show changed files
+        let shown = changes.len().min(SHOWN_CHANGES);
+        let hidden = changes.len() - shown;
+        for (i, change) in changes.iter().take(shown).enumerate() {
+            let is_last = i == shown - 1 && hidden == 0;
+            let connector = if is_last { "└" } else { "├" };
+            println!(
+                "  {} {}",
+                style(connector).dim(),
+                describe_change(change, &root)
+            );
+        }
+        if hidden > 0 {
             println!(
                 "  {} {}",
-                style("●").cyan().bold(),
-                style("Watching for changes...").cyan(),
+                style("└").dim(),
+                style(format!("… and {} more", hidden)).dim(),
             );
-            println!();
+        }
+
+        for (i, files) in triggered {
+            let job = &mut jobs[i];
+            if let Some(current) = job.run.take() {
+                if mode == WatchMode::Queue && !restart_key {
+                    let queued = job.queued.get_or_insert_with(|| {
+                        StyledOutput::dim(&format!(
+                            "Queued: {} runs again once its current run finishes",
+                            job.rule.script
+                        ));
+                        Vec::new()
+                    });
+                    for file in files {
+                        if !queued.contains(&file) {
+                            queued.push(file);
+                        }
+                    }
+                    job.run = Some(current);
+                    continue;
+                }
+                println!(
+                    "  {} {}",
+                    style("↻").yellow(),
+                    style(format!("Restarting {}", job.rule.script)).dim(),
+                );
+                current.stop();
+            }
+
+            job.run = start(&job.rule, &files, keys.is_some());
+            job.started = Instant::now();
         }
     }
 }
 
-fn scan_directory_recursive(
-    dir: &Path,
-    patterns: &[String],
-    file_times: &mut HashMap<PathBuf, SystemTime>,
-) {
-    let entries = match std::fs::read_dir(dir) {
-        Ok(entries) => entries,
-        Err(_) => return,
+impl Job {
+    fn matches(&self, change: &Change, root: &Path) -> bool {
+        let matches = |path: &Path| {
+            relative_path(root, path).is_some_and(|relative| self.globs.is_match(&relative))
+        };
+        match change {
+            Change::Renamed { from, to } => matches(from) || matches(to),
+            change => matches(change.path()),
+        }
+    }
+}
+
+/// The files that exist after `changes`, relative to `root`, for the
+/// `{file}` and `{files}` placeholders
+fn changed_files(changes: &[&Change], root: &Path) -> Vec<String> {
+    changes
+        .iter()
+        .filter(|change| !matches!(change, Change::Removed(_)))
+        .filter_map(|change| relative_path(root, change.path()))
+        .collect()
+}
+
+/// Ignore patterns for what the script writes: its `kn.cache` outputs and
+/// the usual output directories, except those a watch pattern points into
+fn output_ignores(patterns: &[String], cache_outputs: Vec<String>) -> Vec<String> {
+    let first_segment = |pattern: &str| {
+        let pattern = pattern.trim_start_matches("./");
+        pattern.split('/').next().unwrap_or(pattern).to_string()
     };
+    let watched: Vec<String> = patterns
+        .iter()
+        .filter(|p| !p.starts_with('!'))
+        .map(|p| first_segment(p))
+        .collect();
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
+    cache_outputs
+        .into_iter()
+        .chain(OUTPUT_DIRS.iter().map(|dir| format!("{}/", dir)))
+        .filter(|output| !watched.contains(&first_segment(output)))
+        .collect()
+}
 
-        if name.starts_with('.') || name == "node_modules" || name == "target" {
-            continue;
-        }
+/// A changed file with a marker for the kind of change
+fn describe_change(change: &Change, root: &Path) -> String {
+    let relative = |path: &Path| {
+        let path = path.strip_prefix(root).unwrap_or(path);
+        style(path.display().to_string()).dim()
+    };
+    match change {
+        Change::Created(path) => format!("{} {}", style("+").green(), relative(path)),
+        Change::Modified(path) => format!("{} {}", style("~").yellow(), relative(path)),
+        Change::Removed(path) => format!("{} {}", style("-").red(), relative(path)),
+        Change::Renamed { from, to } => format!(
+            "{} {} {} {}",
+            style("»").cyan(),
+            relative(from),
+            style("→").dim(),
+            relative(to)
+        ),
+    }
+}
 
-        if path.is_dir() {
-            scan_directory_recursive(&path, patterns, file_times);
-        } else if path.is_file() && should_watch(&path, patterns) {
-            if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
-                file_times.insert(path, modified);
+/// Start a run of the rule's script for the changed `files`, re-reading
+/// package.json so edits to it apply. `None` when the script cannot be
+/// resolved; the error is shown.
+fn start(rule: &WatchRule, files: &[String], reading_keys: bool) -> Option<RunningSteps> {
+    println!();
+    StyledOutput::separator();
+    println!();
+
+    let steps = std::env::current_dir()
+        .map_err(anyhow::Error::from)
+        .and_then(|cwd| ScriptPackage::find(&cwd, false))
+        .and_then(|package| {
+            let env = EnvOptions::default();
+            if rule.per_file() && !files.is_empty() {
+                // One run of the script after another, a run per file
+                let mut steps = Vec::new();
+                for file in files {
+                    let args = rule.args_for(Some(file), files);
+                    steps.extend(package.script_steps(&rule.script, &args, &env)?);
+                }
+                Ok(steps)
+            } else {
+                package.script_steps(&rule.script, &rule.args_for(None, files), &env)
+            }
+        });
+    match steps {
+        Ok(mut steps) => {
+            if reading_keys {
+                // The keyboard belongs to kn while it listens for restarts
+                for step in &mut steps {
+                    step.command.stdin(Stdio::null());
+                }
             }
+            Some(spawn_steps(steps))
+        }
+        Err(e) => {
+            StyledOutput::error(&format!("Script failed: {}", e));
+            watching();
+            None
         }
     }
 }
 
-fn check_changes_recursive(
-    dir: &Path,
-    patterns: &[String],
-    file_times: &mut HashMap<PathBuf, SystemTime>,
-    changed: &mut Vec<PathBuf>,
-) {
-    let entries = match std::fs::read_dir(dir) {
-        Ok(entries) => entries,
-        Err(_) => return,
-    };
+/// Show how a run that ended by itself went
+fn report(script_name: &str, result: Result<()>, elapsed: Duration) {
+    println!();
+    match result {
+        Ok(()) => StyledOutput::success(&format!(
+            "{} finished in {}",
+            script_name,
+            StyledOutput::duration(elapsed.as_secs_f64())
+        )),
+        Err(e) if find_kn_error(&e).is_some() => StyledOutput::error(&format!(
+            "{} exited with code {}",
+            script_name,
+            exit_code(&e)
+        )),
+        Err(e) => StyledOutput::error(&format!("Script failed: {}", e)),
+    }
+}
 
-    for entry in entries.flatten() {
-        let path = entry.path();
-        let name = entry.file_name().to_string_lossy().to_string();
+fn watching() {
+    println!();
+    println!(
+        "  {} {}",
+        style("●").cyan().bold(),
+        style("Watching for changes...").cyan(),
+    );
+    println!();
+}
 
-        if name.starts_with('.') || name == "node_modules" || name == "target" {
-            continue;
+/// Key presses read from the terminal, which is switched to unbuffered
+/// input without echo until dropped
+struct Keys {
+    receiver: Receiver<u8>,
+    #[cfg(unix)]
+    original: libc::termios,
+}
+
+impl Keys {
+    /// Start reading keys when stdin is a terminal
+    fn start() -> Option<Self> {
+        if !io::stdin().is_terminal() {
+            return None;
         }
 
-        if path.is_dir() {
-            check_changes_recursive(&path, patterns, file_times, changed);
-        } else if path.is_file() && should_watch(&path, patterns) {
-            if let Ok(modified) = entry.metadata().and_then(|m| m.modified()) {
-                if let Some(&last_modified) = file_times.get(&path) {
-                    if modified > last_modified {
-                        changed.push(path.clone());
-                        file_times.insert(path, modified);
-                    }
-                } else {
-                    file_times.insert(path.clone(), modified);
-                    changed.push(path);
+        #[cfg(unix)]
+        let original = unsafe {
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut termios) != 0 {
+                return None;
+            }
+            let original = termios;
+            // Keep ISIG so Ctrl+C still raises SIGINT
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios);
+            original
+        };
+
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut stdin = io::stdin();
+            let mut byte = [0u8; 1];
+            while let Ok(1) = stdin.read(&mut byte) {
+                if sender.send(byte[0]).is_err() {
+                    break;
                 }
             }
-        }
+        });
+
+        Some(Keys {
+            receiver,
+            #[cfg(unix)]
+            original,
+        })
+    }
+
+    /// Whether `r` or Enter was pressed since the last call
+    fn restart_requested(&self) -> bool {
+        // Drains every pending key, so a burst of presses restarts once
+        self.receiver
+            .try_iter()
+            .filter(|key| matches!(key, b'r' | b'R' | b'\n' | b'\r'))
+            .count()
+            > 0
     }
 }
 
-fn should_watch(path: &Path, patterns: &[String]) -> bool {
-    let path_str = path.to_string_lossy();
-
-    for pattern in patterns {
-        if pattern.contains("**") {
-            let parts: Vec<&str> = pattern.split("**").collect();
-            if parts.len() == 2 {
-                let prefix = parts[0].trim_end_matches('/');
-                let suffix = parts[1].trim_start_matches('/');
-                if (prefix.is_empty() || path_str.contains(prefix))
-                    && (suffix == "*" || suffix.is_empty() || path_str.ends_with(suffix))
-                {
-                    return true;
-                }
-            }
-        } else if pattern.starts_with("*.") {
-            let ext = pattern.trim_start_matches("*.");
-            if path_str.ends_with(&format!(".{}", ext)) {
-                return true;
-            }
-        } else if path_str.contains(pattern) {
-            return true;
+impl Drop for Keys {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
         }
     }
-
-    false
 }
@@ -1,12 +1,16 @@
 use anyhow::{bail, Context, Result};
 use indexmap::IndexMap;
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
+use crate::agent_install::ensure_agent;
 use crate::agents::Agent;
+use crate::config::{get_script_runner, ScriptRunner};
 use crate::detect::detect;
+use crate::dotenv::{self, EnvOptions};
 use crate::runner::DetectOptions;
+use crate::script_runner::{native_steps, run_steps, ScriptStep};
 
 #[derive(Debug, Clone, Default, serde::Deserialize)]
 pub struct Package {
@@ -57,53 +61,166 @@ pub fn detect_agent() -> Agent {
     detect(options).unwrap_or(Agent::Npm)
 }
 
+/// The package whose scripts `kn run` works with. Resolved once, so the
+/// script lookup, agent detection and the run itself agree on the package.
+#[derive(Debug, Clone)]
+pub struct ScriptPackage {
+    /// Path of its package.json
+    pub manifest: PathBuf,
+    pub scripts: IndexMap<String, String>,
+    pub agent: Agent,
+}
+
+impl ScriptPackage {
+    /// The nearest package at or above `cwd`, or with `root` the workspace
+    /// root (the nearest package when `cwd` is not in a workspace)
+    pub fn find(cwd: &Path, root: bool) -> Result<Self> {
+        let start = if root {
+            crate::workspace::find_root(cwd).unwrap_or_else(|| cwd.to_path_buf())
+        } else {
+            cwd.to_path_buf()
+        };
+        let (manifest, package) = crate::utils::find_and_parse_package_json(&start)?;
+        let dir = manifest.parent().unwrap_or_else(|| Path::new("."));
+        let agent = detect(DetectOptions {
+            cwd: dir.to_path_buf(),
+            ..Default::default()
+        })
+        .unwrap_or(Agent::Npm);
+
+        Ok(ScriptPackage {
+            scripts: package.scripts.unwrap_or_default(),
+            manifest,
+            agent,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        self.manifest.parent().unwrap_or_else(|| Path::new("."))
+    }
+
+    /// Resolve the commands that run one of the package's scripts
+    pub fn script_steps(
+        &self,
+        script_name: &str,
+        args: &[String],
+        env_options: &EnvOptions,
+    ) -> Result<Vec<ScriptStep>> {
+        package_script_steps(&self.manifest, script_name, args, self.agent, env_options)
+    }
+}
+
+/// Run a script of the nearest package.json, natively or through the
+/// detected package manager depending on the `script_runner` config
 pub fn run_script_fast(script_name: &str, args: &[String]) -> Result<()> {
     let cwd = env::current_dir().context("Failed to get current directory")?;
-    let (package_json_path, package) = crate::utils::find_and_parse_package_json(&cwd)?;
+    let package = ScriptPackage::find(&cwd, false)?;
+    run_steps(package.script_steps(script_name, args, &EnvOptions::default())?)
+}
 
+/// Resolve the commands that run a script of the package at
+/// `package_json_path` with `agent`, with the package's `.env` files loaded
+pub fn package_script_steps(
+    package_json_path: &Path,
+    script_name: &str,
+    args: &[String],
+    agent: Agent,
+    env_options: &EnvOptions,
+) -> Result<Vec<ScriptStep>> {
+    let package_path = package_json_path.parent().unwrap_or_else(|| Path::new("."));
+    let env_vars = dotenv::load(package_path, env_options)?;
+
+    let mut steps = agent_script_steps(package_json_path, script_name, args, agent)?;
+    for step in &mut steps {
+        step.command
+            .envs(env_vars.iter().map(|var| (&var.key, &var.value)));
+    }
+    Ok(steps)
+}
+
+fn agent_script_steps(
+    package_json_path: &Path,
+    script_name: &str,
+    args: &[String],
+    agent: Agent,
+) -> Result<Vec<ScriptStep>> {
+    let package_path = package_json_path.parent().unwrap_or_else(|| Path::new("."));
+    let (_path, package) = crate::utils::find_and_parse_package_json(package_path)?;
     let scripts = match package.scripts {
         Some(s) if !s.is_empty() => s,
         _ => bail!("No scripts found in package.json"),
     };
 
-    let package_path = package_json_path.parent().unwrap_or_else(|| Path::new("."));
+    let Some(script_command) = scripts.get(script_name) else {
+        bail!("Script '{}' not found in package.json", script_name);
+    };
 
-    if let Some(script_command) = scripts.get(script_name) {
-        let agent = detect_agent();
+    if get_script_runner() == ScriptRunner::Native {
+        return native_steps(package_json_path, script_name, args, agent);
+    }
+    ensure_agent(agent, &DetectOptions::new())?;
+
+    // The agent runs from the directory kn was started in and is pointed at
+    // the package, so it sets `INIT_CWD` to where the user is
+    let mut cmd = Command::new(agent.name());
+    cmd.arg(package_dir_flag(agent))
+        .arg(package_path)
+        .args(run_script_args(agent, script_name, args));
+    if env::var_os("INIT_CWD").is_none() {
+        if let Ok(cwd) = env::current_dir() {
+            cmd.env("INIT_CWD", cwd);
+        }
+    }
 
-        let mut cmd = Command::new(agent.name());
-        cmd.arg("run").arg(script_name);
+    cmd.env("npm_lifecycle_event", script_name)
+        .env("npm_lifecycle_script", script_command)
+        .env(
+            "npm_package_json",
+            package_json_path.to_string_lossy().as_ref(),
+        )
+        .env("npm_execpath", env::current_exe().unwrap_or_default());
 
-        // Add additional arguments
-        if !args.is_empty() {
-            // For npm and pnpm, need to add -- before extra args
-            if matches!(agent, Agent::Npm | Agent::Pnpm | Agent::Pnpm6) {
-                cmd.arg("--");
-            }
-            cmd.args(args);
-        }
+    Ok(vec![ScriptStep {
+        event: script_name.to_string(),
+        display: None,
+        command: cmd,
+    }])
+}
 
-        let status = cmd
-            .env("npm_lifecycle_event", script_name)
-            .env("npm_lifecycle_script", script_command)
-            .env(
-                "npm_package_json",
-                package_json_path.to_string_lossy().as_ref(),
-            )
-            .env("npm_execpath", env::current_exe().unwrap_or_default())
-            .current_dir(package_path)
-            .status()
-            .with_context(|| format!("Failed to execute script '{}'", script_name))?;
-
-        if !status.success() {
-            let code = status.code().unwrap_or(1);
-            std::process::exit(code);
-        }
-    } else {
-        bail!("Script '{}' not found in package.json", script_name);
+/// Global flag that makes `agent` operate on the package in another directory
+fn package_dir_flag(agent: Agent) -> &'static str {
+    match agent {
+        Agent::Npm => "--prefix",
+        Agent::Pnpm | Agent::Pnpm6 => "--dir",
+        Agent::Yarn | Agent::YarnBerry | Agent::Bun => "--cwd",
     }
+}
+
+/// Arguments for `<agent> run <script>` that forward `args` to the script
+/// untouched:
+///
+/// - npm, pnpm 6 and bun only forward what follows `--`
+/// - yarn classic forwards everything but reads flags it knows (`--silent`,
+///   `--verbose`, ...) as its own unless they follow `--`, and drops the
+///   `--` itself; it is only added when a flag is present to avoid its warning
+/// - yarn berry and pnpm 7+ forward everything after the script name,
+///   including a `--`, so none is added
+///
+/// Each argument stays a single argv entry, so spaces and quotes need no
+/// escaping.
+pub fn run_script_args(agent: Agent, script_name: &str, args: &[String]) -> Vec<String> {
+    let separator = match agent {
+        Agent::Npm | Agent::Pnpm6 | Agent::Bun => !args.is_empty(),
+        Agent::Yarn => args.iter().any(|arg| arg.starts_with('-')),
+        Agent::YarnBerry | Agent::Pnpm => false,
+    };
 
-    Ok(())
+    let mut run_args = vec!["run".to_string(), script_name.to_string()];
+    if separator {
+        run_args.push("--".to_string());
+    }
+    run_args.extend(args.iter().cloned());
+    run_args
 }
 
 #[cfg(test)]
@@ -166,6 +283,46 @@ mod tests {
         cleanup_test_dir(test_dir);
     }
 
+    #[test]
+    fn test_run_script_args() {
+        let args: Vec<String> = ["--watch", "two words", r#"say "hi""#, "it's"]
+            .iter()
+            .map(|a| a.to_string())
+            .collect();
+        let plain = vec!["src".to_string()];
+        let run = |agent, args: &[String]| run_script_args(agent, "build", args);
+        let expected = |separator: bool, args: &[String]| {
+            let mut v = vec!["run".to_string(), "build".to_string()];
+            if separator {
+                v.push("--".to_string());
+            }
+            v.extend(args.iter().cloned());
+            v
+        };
+
+        for agent in [Agent::Npm, Agent::Pnpm6, Agent::Bun] {
+            assert_eq!(run(agent, &args), expected(true, &args), "{:?}", agent);
+            assert_eq!(run(agent, &plain), expected(true, &plain), "{:?}", agent);
+        }
+        assert_eq!(run(Agent::Yarn, &args), expected(true, &args));
+        assert_eq!(run(Agent::Yarn, &plain), expected(false, &plain));
+        for agent in [Agent::YarnBerry, Agent::Pnpm] {
+            assert_eq!(run(agent, &args), expected(false, &args), "{:?}", agent);
+            assert_eq!(run(agent, &plain), expected(false, &plain), "{:?}", agent);
+        }
+
+        for agent in [
+            Agent::Npm,
+            Agent::Yarn,
+            Agent::YarnBerry,
+            Agent::Pnpm,
+            Agent::Pnpm6,
+            Agent::Bun,
+        ] {
+            assert_eq!(run(agent, &[]), expected(false, &[]), "{:?}", agent);
+        }
+    }
+
     #[test]
     fn test_package_parsing_no_scripts() {
         let test_dir = Path::new("/tmp/kn_test_no_scripts");
@@ -0,0 +1,540 @@
+//! Shell completion scripts (`kn completions <shell>`).
+//!
+//! The scripts are generated from the command table of the CLI parser, so
+//! commands and flags complete without starting kn. Script, dependency and
+//! workspace names depend on the current directory; for those the scripts
+//! call back into the hidden `kn __complete <kind>`.
+
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+use crate::cli_parser::{CommandSpec, Values, COMMANDS, VALUE_FLAGS};
+use crate::command_utils::ScriptPackage;
+use crate::utils::find_up;
+use crate::workspace;
+
+/// Shells `kn completions` supports
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "powershell" | "pwsh" => Some(Shell::PowerShell),
+            _ => None,
+        }
+    }
+}
+
+/// The completion script for `shell`
+pub fn script(shell: Shell) -> String {
+    let template = match shell {
+        Shell::Bash => BASH,
+        Shell::Zsh => ZSH,
+        Shell::Fish => FISH,
+        Shell::PowerShell => POWERSHELL,
+    };
+    let commands: Vec<&str> = COMMANDS
+        .iter()
+        .flat_map(|c| c.names.iter().copied())
+        .collect();
+    let skipped: Vec<&str> = VALUE_FLAGS
+        .iter()
+        .map(|(flag, _)| *flag)
+        .filter(|flag| *flag != "-C")
+        .collect();
+
+    template
+        .replace("@COMMANDS@", &words(shell, &commands))
+        .replace("@SKIP@", &pattern(shell, &skipped))
+        .replace("@VALUE_FLAGS@", &value_flag_cases(shell))
+        .replace("@FLAGS@", &flag_cases(shell))
+        .replace("@POSITIONALS@", &positional_cases(shell))
+}
+
+/// Values of `kind` (`scripts`, `dependencies` or `workspaces`) for `cwd`
+pub fn dynamic_values(kind: &str, cwd: &Path) -> Vec<String> {
+    match kind {
+        "scripts" => ScriptPackage::find(cwd, false)
+            .map(|package| package.scripts.into_keys().collect())
+            .unwrap_or_default(),
+        "dependencies" => dependencies(cwd),
+        "workspaces" => workspace::find_root(cwd)
+            .and_then(|root| workspace::discover(&root).ok())
+            .map(|packages| packages.into_iter().map(|p| p.name).collect())
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Every dependency of the nearest package.json, whatever its kind
+fn dependencies(cwd: &Path) -> Vec<String> {
+    let Some(json) = find_up("package.json", cwd)
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|contents| serde_json::from_str::<Value>(&contents).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut names: Vec<String> = Vec::new();
+    for field in [
+        "dependencies",
+        "devDependencies",
+        "optionalDependencies",
+        "peerDependencies",
+    ] {
+        if let Value::Object(deps) = &json[field] {
+            for name in deps.keys() {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+        }
+    }
+    names
+}
+
+/// The dynamic kind `values` are fetched with, if any
+fn kind(values: Values) -> Option<&'static str> {
+    match values {
+        Values::Scripts => Some("scripts"),
+        Values::Dependencies => Some("dependencies"),
+        Values::Workspaces => Some("workspaces"),
+        _ => None,
+    }
+}
+
+/// Shell code offering `values` as candidates
+fn offer(shell: Shell, values: Values) -> String {
+    if let Some(kind) = kind(values) {
+        return match shell {
+            Shell::Bash => format!("_kn_reply \"$(_kn_dynamic {})\"", kind),
+            Shell::Zsh => format!("compadd -- ${{(f)\"$(_kn_dynamic {})\"}}", kind),
+            Shell::Fish => format!("__kn_dynamic \"$dir\" {}", kind),
+            Shell::PowerShell => format!("$candidates = & $dynamic {}", kind),
+        };
+    }
+    match (shell, values) {
+        (Shell::Bash, Values::Files) => "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string(),
+        (Shell::Bash, Values::Directories) => "COMPREPLY=($(compgen -d -- \"$cur\"))".to_string(),
+        (Shell::Bash, Values::Words(list)) => format!("_kn_reply \"{}\"", list.join(" ")),
+        (Shell::Bash, _) => "COMPREPLY=()".to_string(),
+        (Shell::Zsh, Values::Files) => "_files".to_string(),
+        (Shell::Zsh, Values::Directories) => "_directories".to_string(),
+        (Shell::Zsh, Values::Words(list)) => format!("compadd -- {}", list.join(" ")),
+        (Shell::Zsh, _) => ":".to_string(),
+        (Shell::Fish, Values::Files) => "__fish_complete_path $cur".to_string(),
+        (Shell::Fish, Values::Directories) => "__fish_complete_directories $cur".to_string(),
+        (Shell::Fish, Values::Words(list)) => format!("printf '%s\\n' {}", list.join(" ")),
+        (Shell::Fish, _) => "true".to_string(),
+        // Without candidates PowerShell falls back to completing paths
+        (Shell::PowerShell, Values::Words(list)) => {
+            format!("$candidates = {}", words(shell, list))
+        }
+        (Shell::PowerShell, _) => "return".to_string(),
+    }
+}
+
+/// A list of words in the syntax of `shell`
+fn words(shell: Shell, list: &[&str]) -> String {
+    match shell {
+        Shell::PowerShell => {
+            let quoted: Vec<String> = list.iter().map(|w| format!("'{}'", w)).collect();
+            format!("@({})", quoted.join(", "))
+        }
+        _ => list.join(" "),
+    }
+}
+
+/// A case pattern matching any of `list`
+fn pattern(shell: Shell, list: &[&str]) -> String {
+    match shell {
+        Shell::Bash | Shell::Zsh => list.join("|"),
+        Shell::Fish => list.join(" "),
+        Shell::PowerShell => words(shell, list),
+    }
+}
+
+/// One branch of a case statement on a command or flag, indented by `indent`
+fn case(shell: Shell, indent: usize, names: &[&str], body: &str) -> String {
+    let pad = " ".repeat(indent);
+    match shell {
+        Shell::Bash | Shell::Zsh => format!("{}{}) {} ;;\n", pad, pattern(shell, names), body),
+        Shell::Fish => format!(
+            "{}case {}\n{}    {}\n",
+            pad,
+            pattern(shell, names),
+            pad,
+            body
+        ),
+        Shell::PowerShell => format!(
+            "{}{{ $_ -cin {} }} {{ {}; break }}\n",
+            pad,
+            pattern(shell, names),
+            body
+        ),
+    }
+}
+
+fn value_flag_cases(shell: Shell) -> String {
+    VALUE_FLAGS
+        .iter()
+        .map(|(flag, values)| {
+            let body = match shell {
+                Shell::PowerShell => format!("$done = $true; {}", offer(shell, *values)),
+                _ => format!("{}; return", offer(shell, *values)),
+            };
+            case(shell, 8, &[flag], &body)
+        })
+        .collect()
+}
+
+fn flag_cases(shell: Shell) -> String {
+    COMMANDS
+        .iter()
+        .filter(|spec| !spec.flags.is_empty())
+        .map(|spec| {
+            let indent = if shell == Shell::PowerShell { 16 } else { 12 };
+            case(
+                shell,
+                indent,
+                spec.names,
+                &offer(shell, Values::Words(spec.flags)),
+            )
+        })
+        .collect()
+}
+
+fn positional_cases(shell: Shell) -> String {
+    COMMANDS
+        .iter()
+        .filter(|spec| spec.first != Values::None || spec.rest != Values::None)
+        .map(|spec| {
+            let indent = if shell == Shell::PowerShell { 16 } else { 8 };
+            case(shell, indent, spec.names, &positional(shell, spec))
+        })
+        .collect()
+}
+
+/// Offer the first or the later positional values of a command
+fn positional(shell: Shell, spec: &CommandSpec) -> String {
+    let (first, rest) = (offer(shell, spec.first), offer(shell, spec.rest));
+    if first == rest {
+        return first;
+    }
+    match shell {
+        Shell::Bash => format!(
+            "if [ \"$positional\" -eq 0 ]; then {}; else {}; fi",
+            first, rest
+        ),
+        Shell::Zsh => format!(
+            "if (( positional == 0 )); then {}; else {}; fi",
+            first, rest
+        ),
+        Shell::Fish => format!(
+            "if test $positional -eq 0\n                {}\n            else\n                {}\n            end",
+            first, rest
+        ),
+        Shell::PowerShell => format!(
+            "if ($positional -eq 0) {{ {} }} else {{ {} }}",
+            first, rest
+        ),
+    }
+}
+
+const BASH: &str = r#"# kn completion for bash
+# Add to ~/.bashrc: eval "$(kn completions bash)"
+
+_kn_dynamic() {
+    kn ${dir:+-C "$dir"} __complete "$1" 2>/dev/null
+}
+
+_kn_reply() {
+    COMPREPLY=($(compgen -W "$1" -- "$cur"))
+}
+
+_kn() {
+    # Split the line ourselves: COMP_WORDS breaks script names at ':'
+    local line=${COMP_LINE:0:COMP_POINT} words cur prev cmd= dir= positional=0 i
+    read -ra words <<< "$line"
+    [[ $line == *[[:space:]] ]] && words+=("")
+    cur=${words[${#words[@]}-1]}
+    prev=${words[${#words[@]}-2]}
+
+    for ((i = 1; i < ${#words[@]} - 1; i++)); do
+        case ${words[i]} in
+            -C) dir=${words[i+1]}; ((i++)) ;;
+            @SKIP@) ((i++)) ;;
+            -*) ;;
+            *) if [ -z "$cmd" ]; then cmd=${words[i]}; else ((positional++)); fi ;;
+        esac
+    done
+
+    _kn_complete
+    if [[ $cur == *:* && $COMP_WORDBREAKS == *:* ]]; then
+        local colon=${cur%"${cur##*:}"}
+        COMPREPLY=("${COMPREPLY[@]#"$colon"}")
+    fi
+}
+
+_kn_complete() {
+    case $prev in
+@VALUE_FLAGS@    esac
+
+    if [ -z "$cmd" ]; then
+        _kn_reply "@COMMANDS@"
+        return
+    fi
+
+    if [[ $cur == -* ]]; then
+        case $cmd in
+@FLAGS@        esac
+        return
+    fi
+
+    case $cmd in
+@POSITIONALS@    esac
+}
+
+complete -F _kn kn
+"#;
+
+const ZSH: &str = r#"#compdef kn
+# kn completion for zsh
+# Add to ~/.zshrc: eval "$(kn completions zsh)"
+
+_kn_dynamic() {
+    kn ${dir:+-C "$dir"} __complete "$1" 2>/dev/null
+}
+
+_kn() {
+    local cur=${words[CURRENT]} prev=${words[CURRENT-1]} cmd= dir= positional=0 i
+
+    for ((i = 2; i < CURRENT; i++)); do
+        case ${words[i]} in
+            -C) dir=${words[i+1]}; ((i++)) ;;
+            @SKIP@) ((i++)) ;;
+            -*) ;;
+            *) if [[ -z $cmd ]]; then cmd=${words[i]}; else ((positional++)); fi ;;
+        esac
+    done
+
+    case $prev in
+@VALUE_FLAGS@    esac
+
+    if [[ -z $cmd ]]; then
+        compadd -- @COMMANDS@
+        return
+    fi
+
+    if [[ $cur == -* ]]; then
+        case $cmd in
+@FLAGS@        esac
+        return
+    fi
+
+    case $cmd in
+@POSITIONALS@    esac
+}
+
+compdef _kn kn
+"#;
+
+const FISH: &str = r#"# kn completion for fish
+# Add to ~/.config/fish/config.fish: kn completions fish | source
+
+function __kn_dynamic
+    if test -n "$argv[1]"
+        kn -C $argv[1] __complete $argv[2] 2>/dev/null
+    else
+        kn __complete $argv[2] 2>/dev/null
+    end
+end
+
+function __kn_complete
+    set -l words (commandline -opc)
+    set -l cur (commandline -ct)
+    set -l prev $words[-1]
+    set -l cmd
+    set -l dir
+    set -l positional 0
+
+    set -l i 2
+    while test $i -le (count $words)
+        switch $words[$i]
+            case -C
+                set i (math $i + 1)
+                set dir $words[$i]
+            case @SKIP@
+                set i (math $i + 1)
+            case '-*'
+            case '*'
+                if test -z "$cmd"
+                    set cmd $words[$i]
+                else
+                    set positional (math $positional + 1)
+                end
+        end
+        set i (math $i + 1)
+    end
+
+    switch $prev
+@VALUE_FLAGS@    end
+
+    if test -z "$cmd"
+        printf '%s\n' @COMMANDS@
+        return
+    end
+
+    if string match -q -- '-*' $cur
+        switch $cmd
+@FLAGS@        end
+        return
+    end
+
+    switch $cmd
+@POSITIONALS@    end
+end
+
+complete -c kn -f -a '(__kn_complete)'
+"#;
+
+const POWERSHELL: &str = r#"# kn completion for PowerShell
+# Add to $PROFILE: kn completions powershell | Out-String | Invoke-Expression
+
+Register-ArgumentCompleter -Native -CommandName kn -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+
+    $words = @($commandAst.CommandElements |
+        Where-Object { $_.Extent.EndOffset -le $cursorPosition } |
+        ForEach-Object { $_.ToString() })
+    if ($wordToComplete -and $words.Count -gt 1) {
+        $words = $words[0..($words.Count - 2)]
+    }
+    $prev = $words[-1]
+    $cmd = $null
+    $dir = $null
+    $positional = 0
+
+    for ($i = 1; $i -lt $words.Count; $i++) {
+        $word = $words[$i]
+        if ($word -ceq '-C') { $i++; $dir = $words[$i] }
+        elseif (@SKIP@ -ccontains $word) { $i++ }
+        elseif ($word.StartsWith('-')) { }
+        elseif (-not $cmd) { $cmd = $word }
+        else { $positional++ }
+    }
+
+    $dynamic = {
+        param($kind)
+        if ($dir) { kn -C $dir __complete $kind 2>$null } else { kn __complete $kind 2>$null }
+    }
+
+    $candidates = @()
+    $done = $false
+    switch -CaseSensitive ($prev) {
+@VALUE_FLAGS@    }
+
+    if (-not $done) {
+        if (-not $cmd) {
+            $candidates = @COMMANDS@
+        }
+        elseif ($wordToComplete.StartsWith('-')) {
+            switch -CaseSensitive ($cmd) {
+@FLAGS@            }
+        }
+        else {
+            switch -CaseSensitive ($cmd) {
+@POSITIONALS@            }
+        }
+    }
+
+    $candidates |
+        Where-Object { $_ -like "$wordToComplete*" } |
+        ForEach-Object { [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_) }
+}
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn cleanup_test_dir(dir: &Path) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).expect("Failed to cleanup test directory");
+        }
+    }
+
+    #[test]
+    fn test_scripts_cover_commands_and_flags() {
+        for name in SHELLS {
+            let shell = Shell::from_name(name).unwrap();
+            let script = script(shell);
+            for placeholder in ["@COMMANDS@", "@SKIP@", "@VALUE_FLAGS@", "@FLAGS@"] {
+                assert!(
+                    !script.contains(placeholder),
+                    "{} has {}",
+                    name,
+                    placeholder
+                );
+            }
+            for command in ["install", "run", "clean-install", "completions"] {
+                assert!(script.contains(command), "{} lacks {}", name, command);
+            }
+            for flag in ["--if-present", "--save-dev", "--filter"] {
+                assert!(script.contains(flag), "{} lacks {}", name, flag);
+            }
+            for kind in ["scripts", "dependencies", "workspaces"] {
+                assert!(script.contains(kind), "{} never asks for {}", name, kind);
+            }
+        }
+    }
+
+    #[test]
+    fn test_dynamic_values() {
+        let test_dir = PathBuf::from("/tmp/kn_test_completions");
+        cleanup_test_dir(&test_dir);
+        let app = test_dir.join("packages/app");
+        fs::create_dir_all(&app).unwrap();
+        fs::write(
+            test_dir.join("package.json"),
+            r#"{"name": "root", "workspaces": ["packages/*"], "scripts": {"lint": "eslint ."}}"#,
+        )
+        .unwrap();
+        fs::write(
+            app.join("package.json"),
+            r#"{
+                "name": "@repo/app",
+                "scripts": {"build": "tsc", "build:watch": "tsc -w"},
+                "dependencies": {"react": "^18.0.0"},
+                "devDependencies": {"typescript": "^5.0.0", "react": "^18.0.0"}
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            dynamic_values("scripts", &app),
+            vec!["build", "build:watch"]
+        );
+        assert_eq!(
+            dynamic_values("dependencies", &app),
+            vec!["react", "typescript"]
+        );
+        assert_eq!(dynamic_values("workspaces", &app), vec!["@repo/app"]);
+        assert!(dynamic_values("unknown", &app).is_empty());
+
+        cleanup_test_dir(&test_dir);
+    }
+}
@@ -14,9 +14,33 @@ pub enum DefaultAgent {
     Agent(Agent),
 }
 
+/// How `kn run` executes package.json scripts
+#[derive(PartialEq, Clone, Copy, Debug, Eq, Hash)]
+pub enum ScriptRunner {
+    /// Run the script command through the shell directly
+    Native,
+    /// Delegate to `<agent> run <script>`
+    Agent,
+}
+
+impl ScriptRunner {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "native" => Some(ScriptRunner::Native),
+            "agent" | "package-manager" => Some(ScriptRunner::Agent),
+            _ => None,
+        }
+    }
+}
+
 pub struct Config {
     default_agent: DefaultAgent,
     global_agent: Agent,
+    script_runner: ScriptRunner,
+    /// Where `kn run` writes a log of every run
+    logs_dir: Option<PathBuf>,
+    /// What `kn clean` removes in packages without a `kn.clean` field
+    clean_targets: Option<Vec<String>>,
 }
 
 impl Default for Config {
@@ -24,6 +48,9 @@ impl Default for Config {
         Config {
             default_agent: DefaultAgent::Prompt,
             global_agent: Agent::Npm,
+            script_runner: ScriptRunner::Native,
+            logs_dir: None,
+            clean_targets: None,
         }
     }
 }
@@ -54,6 +81,28 @@ impl Config {
                             config.global_agent = agent;
                         }
                     }
+                    if let Some(script_runner) = section.get("script_runner") {
+                        if let Some(runner) = ScriptRunner::from_name(script_runner) {
+                            config.script_runner = runner;
+                        }
+                    }
+                    if let Some(logs_dir) = section.get("logs_dir").filter(|d| !d.is_empty()) {
+                        config.logs_dir = Some(match logs_dir.strip_prefix("~/") {
+                            Some(rest) => home.join(rest),
+                            None => PathBuf::from(logs_dir),
+                        });
+                    }
+                    if let Some(targets) = section.get("clean_targets") {
+                        let targets: Vec<String> = targets
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|t| !t.is_empty())
+                            .map(String::from)
+                            .collect();
+                        if !targets.is_empty() {
+                            config.clean_targets = Some(targets);
+                        }
+                    }
                 }
             }
         }
@@ -89,3 +138,17 @@ pub fn get_global_agent() -> Agent {
     let Config { global_agent, .. } = get_config();
     global_agent
 }
+
+pub fn get_script_runner() -> ScriptRunner {
+    Config::default().assign().script_runner
+}
+
+/// The `logs_dir` config; relative paths are relative to the package
+pub fn get_logs_dir() -> Option<PathBuf> {
+    Config::default().assign().logs_dir
+}
+
+/// The `clean_targets` config, a comma-separated list of globs
+pub fn get_clean_targets() -> Option<Vec<String>> {
+    Config::default().assign().clean_targets
+}
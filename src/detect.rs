@@ -64,6 +64,28 @@ pub fn detect(options: crate::runner::DetectOptions) -> Option<Agent> {
     detect_from_lock_files(&cwd)
 }
 
+/// Read the `packageManager` field of the nearest package.json as (name, version).
+/// Corepack hashes such as `+sha512.abc` are stripped from the version.
+pub fn package_manager_spec(cwd: &Path) -> Option<(String, Option<String>)> {
+    let package_json_path = crate::utils::find_up("package.json", cwd)?;
+    let contents = fs::read_to_string(package_json_path).ok()?;
+    let json: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    let spec = json.get("packageManager")?.as_str()?;
+    Some(parse_package_manager_spec(spec))
+}
+
+fn parse_package_manager_spec(spec: &str) -> (String, Option<String>) {
+    let spec = spec.strip_prefix('^').unwrap_or(spec);
+    match spec.split_once('@') {
+        Some((name, version)) => {
+            let version = version.split('+').next().unwrap_or(version);
+            let version = (!version.is_empty()).then(|| version.to_string());
+            (name.to_string(), version)
+        }
+        None => (spec.to_string(), None),
+    }
+}
+
 fn detect_from_lock_files(cwd: &Path) -> Option<Agent> {
     let lock_files = [
         ("pnpm-lock.yaml", "pnpm"),
@@ -150,6 +172,7 @@ mod tests {
             cwd: test_dir.to_path_buf(),
             auto_install: false,
             programmatic: true,
+            env: Vec::new(),
         };
 
         let detected = detect(options);
@@ -178,6 +201,7 @@ mod tests {
             cwd: test_dir.to_path_buf(),
             auto_install: false,
             programmatic: true,
+            env: Vec::new(),
         };
 
         let detected = detect(options);
@@ -206,6 +230,7 @@ mod tests {
             cwd: test_dir.to_path_buf(),
             auto_install: false,
             programmatic: true,
+            env: Vec::new(),
         };
 
         let detected = detect(options);
@@ -234,6 +259,7 @@ mod tests {
             cwd: test_dir.to_path_buf(),
             auto_install: false,
             programmatic: true,
+            env: Vec::new(),
         };
 
         let detected = detect(options);
@@ -261,6 +287,7 @@ mod tests {
             cwd: yarn_test_dir.to_path_buf(),
             auto_install: false,
             programmatic: true,
+            env: Vec::new(),
         };
 
         let detected = detect(options.clone());
@@ -283,6 +310,7 @@ mod tests {
             cwd: pnpm_test_dir.to_path_buf(),
             auto_install: false,
             programmatic: true,
+            env: Vec::new(),
         };
 
         let detected = detect(pnpm_options);
@@ -302,6 +330,19 @@ mod tests {
         assert!(AGENT_MAP.iter().any(|(name, _)| *name == "pnpm@6"));
     }
 
+    #[test]
+    fn test_parse_package_manager_spec() {
+        assert_eq!(
+            parse_package_manager_spec("pnpm@8.6.0"),
+            ("pnpm".to_string(), Some("8.6.0".to_string()))
+        );
+        assert_eq!(
+            parse_package_manager_spec("yarn@4.1.0+sha512.5b7bc055cad63273"),
+            ("yarn".to_string(), Some("4.1.0".to_string()))
+        );
+        assert_eq!(parse_package_manager_spec("bun"), ("bun".to_string(), None));
+    }
+
     #[test]
     fn test_fallback_to_npm() {
         let test_dir = Path::new("/tmp/kn_test_fallback");
@@ -320,6 +361,7 @@ mod tests {
             cwd: test_dir.to_path_buf(),
             auto_install: false,
             programmatic: true,
+            env: Vec::new(),
         };
 
         let detected = detect(options);
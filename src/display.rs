@@ -1,4 +1,4 @@
-use console::{style, Color, Style, Term};
+use console::{measure_text_width, pad_str, style, Alignment, Color, Style, Term};
 use indicatif::{ProgressBar, ProgressStyle};
 
 const DESCRIPTION: &str = "Minimal, blazing fast Node.js package manager runner";
@@ -172,6 +172,19 @@ impl StyledOutput {
         println!("  {} {}", style(connector).dim(), text);
     }
 
+    /// An item below a tree item; `parent_is_last` is whether that item was
+    /// the last of its level
+    pub fn tree_child(text: &str, parent_is_last: bool, is_last: bool) {
+        let rail = if parent_is_last { " " } else { "│" };
+        let connector = if is_last { "└" } else { "├" };
+        println!(
+            "  {} {} {}",
+            style(rail).dim(),
+            style(connector).dim(),
+            text
+        );
+    }
+
     // ════════════════════════════════════════════════
     //  Spinner
     // ════════════════════════════════════════════════
@@ -194,17 +207,64 @@ impl StyledOutput {
     // ════════════════════════════════════════════════
 
     pub fn completion(duration_secs: f64) {
-        let duration_text = if duration_secs < 1.0 {
+        println!(
+            "\n  {} {}",
+            style("✨").bold(),
+            style(format!("Done in {}", Self::duration(duration_secs)))
+                .green()
+                .italic(),
+        );
+    }
+
+    /// Human-readable duration (`350ms`, `2.41s`)
+    pub fn duration(duration_secs: f64) -> String {
+        if duration_secs < 1.0 {
             format!("{:.0}ms", duration_secs * 1000.0)
         } else {
             format!("{:.2}s", duration_secs)
+        }
+    }
+
+    // ════════════════════════════════════════════════
+    //  Table
+    //
+    //    Script      Duration  Exit
+    //    ──────────────────────────
+    //    ✔ lint      1.20s     0
+    //
+    // ════════════════════════════════════════════════
+
+    /// Render rows as aligned columns; cells may contain ANSI styling
+    pub fn table(headers: &[&str], rows: &[Vec<String>]) {
+        let columns = headers.len();
+        let mut widths: Vec<usize> = headers.iter().map(|h| measure_text_width(h)).collect();
+        for row in rows {
+            for (i, cell) in row.iter().enumerate().take(columns) {
+                widths[i] = widths[i].max(measure_text_width(cell));
+            }
+        }
+
+        let render = |cells: Vec<String>| {
+            let line: Vec<String> = cells
+                .iter()
+                .enumerate()
+                .map(|(i, cell)| pad_str(cell, widths[i], Alignment::Left, None).to_string())
+                .collect();
+            line.join("  ").trim_end().to_string()
         };
 
-        println!(
-            "\n  {} {}",
-            style("✨").bold(),
-            style(format!("Done in {}", duration_text)).green().italic(),
-        );
+        let header_cells = headers
+            .iter()
+            .map(|h| format!("{}", style(h).bold().dim()))
+            .collect();
+        Self::body(&render(header_cells));
+
+        let total = widths.iter().sum::<usize>() + 2 * columns.saturating_sub(1);
+        Self::body(&format!("{}", style("─".repeat(total)).dim()));
+
+        for row in rows {
+            Self::body(&render(row.clone()));
+        }
     }
 
     // ════════════════════════════════════════════════
@@ -265,6 +325,8 @@ impl StyledOutput {
         println!();
         Self::help_cmd("run", "r", "Run scripts from package.json");
         Self::help_cmd("list", "ls", "List available scripts");
+        Self::help_cmd("history", "", "Show recent script runs and timings");
+        Self::help_cmd("cache", "", "Inspect or prune cached script runs");
         Self::help_cmd("watch", "w", "Watch files and re-run script");
         Self::help_cmd("execute", "x, exec", "Execute package binaries");
         println!();
@@ -276,7 +338,8 @@ impl StyledOutput {
             style("Project").bold().fg(COLOR_PRIMARY)
         );
         println!();
-        Self::help_cmd("info", "env", "Show environment information");
+        Self::help_cmd("info", "", "Show environment information");
+        Self::help_cmd("env", "", "Show variables loaded from .env files");
         Self::help_cmd("view", "", "View package info from registry");
         Self::help_cmd("clean", "", "Clean node_modules, cache, etc.");
         println!();
@@ -289,6 +352,7 @@ impl StyledOutput {
         );
         println!();
         Self::help_cmd("upgrade-self", "", "Upgrade kn to latest version");
+        Self::help_cmd("completions", "", "Print shell completion script");
         Self::help_cmd("help", "-h", "Show this help");
         Self::help_cmd("--version", "-v", "Show version number");
         println!();
@@ -0,0 +1,75 @@
+use std::fmt;
+
+use crate::agents::Agent;
+
+/// Errors that determine how kn exits, instead of calling `process::exit`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KnError {
+    /// A child process failed; its own output already explains why
+    Exit(i32),
+    /// The user cancelled an interactive prompt
+    Cancelled,
+    /// The package manager does not support the requested operation
+    Unsupported {
+        agent: Agent,
+        operation: &'static str,
+    },
+}
+
+impl KnError {
+    /// The process exit code this error maps to
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            KnError::Exit(code) => *code,
+            KnError::Cancelled | KnError::Unsupported { .. } => 1,
+        }
+    }
+
+    /// Whether the error has already been reported to the user
+    pub fn is_silent(&self) -> bool {
+        matches!(self, KnError::Exit(_) | KnError::Cancelled)
+    }
+}
+
+impl fmt::Display for KnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KnError::Exit(code) => write!(f, "Process exited with code {}", code),
+            KnError::Cancelled => write!(f, "Cancelled"),
+            KnError::Unsupported { agent, operation } => {
+                write!(f, "{} does not support {}", agent, operation)
+            }
+        }
+    }
+}
+
+impl std::error::Error for KnError {}
+
+/// Find the `KnError` behind an error chain, if any
+pub fn find_kn_error(err: &anyhow::Error) -> Option<&KnError> {
+    err.chain().find_map(|e| e.downcast_ref::<KnError>())
+}
+
+/// Exit code for an arbitrary error (1 unless it carries a `KnError`)
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    find_kn_error(err).map(KnError::exit_code).unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_code_through_context() {
+        let err = Err::<(), _>(KnError::Exit(42))
+            .context("Failed to run script")
+            .unwrap_err();
+        assert_eq!(exit_code(&err), 42);
+        assert!(find_kn_error(&err).is_some_and(KnError::is_silent));
+
+        let err = anyhow::anyhow!("plain failure");
+        assert_eq!(exit_code(&err), 1);
+        assert!(find_kn_error(&err).is_none());
+    }
+}
@@ -0,0 +1,123 @@
+//! Subsequence fuzzy matching for the script picker.
+//!
+//! A query matches when its characters appear in order in the candidate
+//! (case-insensitively). Scoring favours consecutive runs and matches at word
+//! boundaries such as `build:prod`'s `p`, so `bp` ranks `build:prod` above
+//! `bump-deps`.
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_CONSECUTIVE: i64 = 15;
+const BONUS_BOUNDARY: i64 = 10;
+const BONUS_FIRST_CHAR: i64 = 5;
+const BONUS_CASE: i64 = 1;
+const PENALTY_GAP: i64 = 1;
+/// Cap on the penalty for unmatched text before the first and after the last match
+const MAX_EDGE_PENALTY: i64 = 10;
+
+/// A successful match: its score and the matched character indices
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    pub positions: Vec<usize>,
+}
+
+/// Match `query` against `candidate`; an empty query matches everything
+pub fn fuzzy_match(query: &str, candidate: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().filter(|c| !c.is_whitespace()).collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0,
+            positions: Vec::new(),
+        });
+    }
+
+    let text: Vec<char> = candidate.chars().collect();
+    let lower: Vec<char> = text.iter().map(|c| to_lower(*c)).collect();
+    let first = to_lower(query[0]);
+
+    // Try every start of the first query character and keep the best
+    // greedy match from there
+    (0..text.len())
+        .filter(|&start| lower[start] == first)
+        .filter_map(|start| match_from(&query, &text, &lower, start))
+        .max_by(|a, b| a.score.cmp(&b.score).then(b.positions.cmp(&a.positions)))
+}
+
+fn match_from(query: &[char], text: &[char], lower: &[char], start: usize) -> Option<FuzzyMatch> {
+    let mut positions = Vec::with_capacity(query.len());
+    let mut index = start;
+    for q in query {
+        let q = to_lower(*q);
+        while index < text.len() && lower[index] != q {
+            index += 1;
+        }
+        if index == text.len() {
+            return None;
+        }
+        positions.push(index);
+        index += 1;
+    }
+
+    let trailing = text.len() - positions[positions.len() - 1] - 1;
+    let mut score = -(start as i64 * PENALTY_GAP).min(MAX_EDGE_PENALTY)
+        - (trailing as i64 * PENALTY_GAP).min(MAX_EDGE_PENALTY);
+    for (i, &pos) in positions.iter().enumerate() {
+        score += SCORE_MATCH;
+        if text[pos] == query[i] {
+            score += BONUS_CASE;
+        }
+        if pos == 0 {
+            score += BONUS_FIRST_CHAR + BONUS_BOUNDARY;
+        } else if is_boundary(text[pos - 1], text[pos]) {
+            score += BONUS_BOUNDARY;
+        }
+        if i > 0 {
+            let gap = pos - positions[i - 1] - 1;
+            if gap == 0 {
+                score += BONUS_CONSECUTIVE;
+            } else {
+                score -= gap as i64 * PENALTY_GAP;
+            }
+        }
+    }
+
+    Some(FuzzyMatch { score, positions })
+}
+
+fn is_boundary(previous: char, current: char) -> bool {
+    matches!(previous, ':' | '-' | '_' | ' ' | '/' | '.' | '@')
+        || (previous.is_lowercase() && current.is_uppercase())
+}
+
+fn to_lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        let m = fuzzy_match("bp", "build:prod").unwrap();
+        assert_eq!(m.positions, [0, 6]);
+
+        assert!(
+            fuzzy_match("bp", "build:prod").unwrap().score
+                > fuzzy_match("bp", "bump-deps").unwrap().score
+        );
+        assert!(
+            fuzzy_match("build", "build").unwrap().score
+                > fuzzy_match("build", "build:prod").unwrap().score
+        );
+        assert!(
+            fuzzy_match("test", "test:unit").unwrap().score
+                > fuzzy_match("test", "e2e:setup:test").unwrap().score
+        );
+
+        assert_eq!(fuzzy_match("TSC", "tsc -p .").unwrap().positions, [0, 1, 2]);
+        assert_eq!(fuzzy_match("", "anything").unwrap().score, 0);
+        assert!(fuzzy_match("xyz", "build").is_none());
+        assert!(fuzzy_match("dliub", "build").is_none());
+    }
+}
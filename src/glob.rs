@@ -0,0 +1,493 @@
+//! Glob patterns for `kn watch` and the script cache.
+//!
+//! Patterns match `/`-separated paths relative to the project root. `*` and
+//! `?` stay within one path segment, `**` spans any number of segments,
+//! `[a-z]` and `[!a-z]` match one character of a class, and `{a,b}` expands to
+//! alternatives. A pattern without wildcards names a file or directory and
+//! also matches everything below it, as does a pattern ending in `/`.
+//!
+//! [`Glob::with_separator`] matches other names the same way, such as
+//! `:`-separated script names.
+
+use std::path::{Component, Path};
+
+/// A compiled glob pattern
+#[derive(Debug, Clone)]
+pub struct Glob {
+    alternatives: Vec<Alternative>,
+    separator: char,
+}
+
+/// One pattern of a brace expansion
+#[derive(Debug, Clone)]
+struct Alternative {
+    segments: Vec<Segment>,
+    /// Whether paths below a match match too
+    prefix: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    /// `**`: zero or more whole segments
+    AnyDepth,
+    Tokens(Vec<Token>),
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Char(char),
+    /// `?`
+    AnyChar,
+    /// `*`
+    AnyRun,
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Self {
+        let alternatives = expand_braces(pattern)
+            .iter()
+            .map(|pattern| Alternative::new(pattern))
+            .collect();
+        Glob {
+            alternatives,
+            separator: '/',
+        }
+    }
+
+    /// A pattern over names made of segments joined by `separator`. Unlike
+    /// paths, names match whole and a trailing `**` needs at least one
+    /// segment, so `build:**` matches `build:css:min` but not `build`.
+    pub fn with_separator(pattern: &str, separator: char) -> Self {
+        let alternatives = expand_braces(pattern)
+            .iter()
+            .map(|pattern| {
+                let mut segments = parse_segments(pattern, separator);
+                if let Some(Segment::AnyDepth) = segments.last() {
+                    segments.insert(segments.len() - 1, Segment::Tokens(vec![Token::AnyRun]));
+                }
+                Alternative {
+                    segments,
+                    prefix: false,
+                }
+            })
+            .collect();
+        Glob {
+            alternatives,
+            separator,
+        }
+    }
+
+    /// Whether the relative path `path` matches
+    pub fn is_match(&self, path: &str) -> bool {
+        let parts: Vec<&str> = path
+            .split(self.separator)
+            .filter(|p| !p.is_empty())
+            .collect();
+        self.alternatives.iter().any(|alt| {
+            if alt.prefix {
+                (1..=parts.len()).any(|n| segments_match(&alt.segments, &parts[..n]))
+            } else {
+                segments_match(&alt.segments, &parts)
+            }
+        })
+    }
+}
+
+impl Alternative {
+    fn new(pattern: &str) -> Self {
+        let mut pattern = pattern;
+        while let Some(rest) = pattern.strip_prefix("./") {
+            pattern = rest;
+        }
+        let trailing_slash = pattern.ends_with('/');
+        let segments = parse_segments(pattern, '/');
+        let literal = segments.iter().all(|segment| match segment {
+            Segment::AnyDepth => false,
+            Segment::Tokens(tokens) => tokens.iter().all(|t| matches!(t, Token::Char(_))),
+        });
+
+        Alternative {
+            segments,
+            prefix: literal || trailing_slash,
+        }
+    }
+}
+
+fn parse_segments(pattern: &str, separator: char) -> Vec<Segment> {
+    pattern
+        .split(separator)
+        .filter(|s| !s.is_empty())
+        .map(|s| match s {
+            "**" => Segment::AnyDepth,
+            s => Segment::Tokens(tokenize(s)),
+        })
+        .collect()
+}
+
+/// Include patterns, minus `!`-prefixed and ignore patterns
+#[derive(Debug, Clone, Default)]
+pub struct GlobSet {
+    include: Vec<Glob>,
+    exclude: Vec<Glob>,
+}
+
+impl GlobSet {
+    /// Patterns starting with `!` exclude paths instead of including them
+    pub fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        let mut set = GlobSet::default();
+        for pattern in patterns {
+            match pattern.as_ref().strip_prefix('!') {
+                Some(excluded) => set.exclude.push(Glob::new(excluded)),
+                None => set.include.push(Glob::new(pattern.as_ref())),
+            }
+        }
+        set
+    }
+
+    /// Exclude paths matching any of `patterns` (a leading `!` is optional)
+    pub fn ignore<S: AsRef<str>>(&mut self, patterns: &[S]) {
+        for pattern in patterns {
+            let pattern = pattern.as_ref();
+            self.exclude
+                .push(Glob::new(pattern.strip_prefix('!').unwrap_or(pattern)));
+        }
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        self.include.iter().any(|g| g.is_match(path)) && !self.is_excluded(path)
+    }
+
+    /// Whether an exclude pattern matches `path`; for a directory this means
+    /// nothing below it can match either
+    pub fn is_excluded(&self, path: &str) -> bool {
+        self.exclude.iter().any(|g| g.is_match(path))
+    }
+}
+
+/// Match a single pattern against a relative path
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    Glob::new(pattern).is_match(path)
+}
+
+/// `path` relative to `root` with `/` separators, `None` outside of `root`
+pub fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    let parts: Vec<String> = relative
+        .components()
+        .map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    Some(parts.join("/"))
+}
+
+/// Match path segments, backtracking to the last `**` on a mismatch
+fn segments_match(pattern: &[Segment], path: &[&str]) -> bool {
+    wildcard_match(
+        pattern,
+        path,
+        |segment| matches!(segment, Segment::AnyDepth),
+        |segment, part| match segment {
+            Segment::AnyDepth => false,
+            Segment::Tokens(tokens) => {
+                let chars: Vec<char> = part.chars().collect();
+                tokens_match(tokens, &chars)
+            }
+        },
+    )
+}
+
+/// Match one segment, backtracking to the last `*` on a mismatch
+fn tokens_match(tokens: &[Token], text: &[char]) -> bool {
+    wildcard_match(
+        tokens,
+        text,
+        |token| matches!(token, Token::AnyRun),
+        |token, &c| token_matches(token, c),
+    )
+}
+
+/// Match `text` against `pattern`, where elements for which `is_run` holds
+/// take any number of items and every other element takes one item it
+/// matches. On a mismatch only the last run is retried with one more item:
+/// earlier runs could only take less, so the cost stays polynomial however
+/// many runs there are.
+fn wildcard_match<P, T>(
+    pattern: &[P],
+    text: &[T],
+    is_run: impl Fn(&P) -> bool,
+    matches: impl Fn(&P, &T) -> bool,
+) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some(element) if is_run(element) => {
+                backtrack = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some(element) if matches(element, &text[t]) => {
+                p += 1;
+                t += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((run, matched)) => {
+                p = run + 1;
+                t = matched + 1;
+                backtrack = Some((run, matched + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(is_run)
+}
+
+fn token_matches(token: &Token, c: char) -> bool {
+    match token {
+        Token::Char(expected) => *expected == c,
+        Token::AnyChar => true,
+        Token::AnyRun => false,
+        Token::Class { negated, ranges } => {
+            ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&c)) != *negated
+        }
+    }
+}
+
+fn tokenize(segment: &str) -> Vec<Token> {
+    let chars: Vec<char> = segment.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                tokens.push(Token::Char(chars[i + 1]));
+                i += 2;
+                continue;
+            }
+            '*' => {
+                // `a**b` within a segment is just `a*b`
+                if !matches!(tokens.last(), Some(Token::AnyRun)) {
+                    tokens.push(Token::AnyRun);
+                }
+            }
+            '?' => tokens.push(Token::AnyChar),
+            '[' => {
+                if let Some((class, end)) = parse_class(&chars, i) {
+                    tokens.push(class);
+                    i = end + 1;
+                    continue;
+                }
+                // An unclosed `[` is a literal
+                tokens.push(Token::Char('['));
+            }
+            c => tokens.push(Token::Char(c)),
+        }
+        i += 1;
+    }
+    tokens
+}
+
+/// Parse the class starting at `chars[start] == '['`, returning it and the
+/// index of its closing `]`
+fn parse_class(chars: &[char], start: usize) -> Option<(Token, usize)> {
+    let mut i = start + 1;
+    let negated = matches!(chars.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+
+    let mut ranges = Vec::new();
+    let first = i;
+    while i < chars.len() {
+        let mut c = chars[i];
+        // `]` right after the opening bracket is a member, not the end
+        if c == ']' && i > first {
+            return Some((Token::Class { negated, ranges }, i));
+        }
+        if c == '\\' && i + 1 < chars.len() {
+            i += 1;
+            c = chars[i];
+        }
+        if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&end| end != ']') {
+            ranges.push((c, chars[i + 2]));
+            i += 3;
+        } else {
+            ranges.push((c, c));
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Expand `{a,b}` alternatives, which may nest and contain `/`. Braces
+/// without a comma or without a closing brace are literal.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => {
+                if let Some((end, commas)) = closing_brace(&chars, i) {
+                    if !commas.is_empty() {
+                        let prefix: String = chars[..i].iter().collect();
+                        let suffix: String = chars[end + 1..].iter().collect();
+                        let mut bounds = vec![i];
+                        bounds.extend(&commas);
+                        bounds.push(end);
+
+                        return bounds
+                            .windows(2)
+                            .flat_map(|w| {
+                                let choice: String = chars[w[0] + 1..w[1]].iter().collect();
+                                expand_braces(&format!("{}{}{}", prefix, choice, suffix))
+                            })
+                            .collect();
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    vec![pattern.to_string()]
+}
+
+/// Find the `}` closing the brace at `chars[open]` and its top-level commas
+fn closing_brace(chars: &[char], open: usize) -> Option<(usize, Vec<usize>)> {
+    let mut depth = 0;
+    let mut commas = Vec::new();
+    let mut i = open;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((i, commas));
+                }
+            }
+            ',' if depth == 1 => commas.push(i),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_star_stays_in_segment() {
+        // `*.js` only matches at the root
+        assert!(glob_match("*.js", "index.js"));
+        assert!(!glob_match("*.js", "src/index.js"));
+        assert!(glob_match("**/*.js", "src/index.js"));
+        assert!(glob_match("**/*.js", "index.js"));
+        assert!(glob_match("src/*", "src/a.ts"));
+        assert!(!glob_match("src/*", "src/lib/a.ts"));
+        assert!(glob_match("*", ".env"));
+    }
+
+    #[test]
+    fn test_globstar_anchored_at_root() {
+        assert!(glob_match("src/**/*.ts", "src/index.ts"));
+        assert!(glob_match("src/**/*.ts", "src/a/b/c.ts"));
+        // Substring matching used to accept these
+        assert!(!glob_match("src/**/*.ts", "lib/src/index.ts"));
+        assert!(!glob_match("src/**/*.ts", "mysrc/index.ts"));
+        assert!(!glob_match("src/**/*.ts", "src/index.tsx"));
+        assert!(glob_match("src/**", "src"));
+        assert!(glob_match("**/test/**", "packages/app/test/unit/a.js"));
+        assert!(!glob_match("**/test/**", "packages/app/tests/a.js"));
+        assert!(glob_match("a/**/b/**/c", "a/b/c"));
+        assert!(glob_match("a**b", "axxb"));
+
+        // Many globstars on a deep path must not try every split
+        let deep = vec!["a"; 40].join("/");
+        assert!(!glob_match("**/a/**/a/**/a/**/a/**/a/**/b", &deep));
+        assert!(glob_match("**/a/**/a/**/a/**/a/**/a/**", &deep));
+    }
+
+    #[test]
+    fn test_question_mark_and_classes() {
+        assert!(glob_match("file?.txt", "file1.txt"));
+        assert!(!glob_match("file?.txt", "file10.txt"));
+        assert!(!glob_match("?", "a/b"));
+        assert!(glob_match("[abc].js", "b.js"));
+        assert!(!glob_match("[abc].js", "d.js"));
+        assert!(glob_match("v[0-9].md", "v7.md"));
+        assert!(!glob_match("v[0-9].md", "vx.md"));
+        assert!(glob_match("[!.]*", "index.ts"));
+        assert!(!glob_match("[!.]*", ".env"));
+        assert!(glob_match("[^a-c]", "d"));
+        assert!(glob_match("[]]", "]"));
+        assert!(glob_match("[a-]", "-"));
+        // Unclosed classes and escapes are literal
+        assert!(glob_match("a[b", "a[b"));
+        assert!(glob_match("\\*.js", "*.js"));
+        assert!(!glob_match("\\*.js", "a.js"));
+    }
+
+    #[test]
+    fn test_braces() {
+        assert!(glob_match("*.{js,ts}", "a.ts"));
+        assert!(glob_match("*.{js,ts}", "a.js"));
+        assert!(!glob_match("*.{js,ts}", "a.css"));
+        assert!(glob_match("{src,test}/**/*.ts", "test/a/b.ts"));
+        assert!(glob_match("src/{a,b/{c,d}}.ts", "src/b/d.ts"));
+        assert!(glob_match("{lib/x,src}/*.js", "lib/x/y.js"));
+        assert!(glob_match("a{b}c", "a{b}c"));
+        assert!(glob_match("a{b", "a{b"));
+        assert_eq!(expand_braces("{a,b}{1,2}").len(), 4);
+    }
+
+    #[test]
+    fn test_literal_paths_match_below() {
+        assert!(glob_match("src", "src/index.ts"));
+        assert!(glob_match("src/", "src/a/b.ts"));
+        assert!(glob_match("./tsconfig.json", "tsconfig.json"));
+        assert!(!glob_match("src", "srcs/index.ts"));
+        assert!(!glob_match("src/*.ts", "src/lib/a.ts"));
+    }
+
+    #[test]
+    fn test_glob_set_negation_and_ignores() {
+        let mut set = GlobSet::new(&["src/**/*.ts", "!src/**/*.test.ts", "*.json"]);
+        assert!(set.is_match("src/app.ts"));
+        assert!(!set.is_match("src/app.test.ts"));
+        assert!(set.is_match("package.json"));
+        assert!(!set.is_match("README.md"));
+
+        set.ignore(&["src/generated", "!*.lock.json"]);
+        assert!(!set.is_match("src/generated/api.ts"));
+        assert!(set.is_excluded("src/generated"));
+        assert!(!set.is_match("deps.lock.json"));
+        assert!(set.is_match("src/api.ts"));
+
+        assert!(!GlobSet::new(&["!src/**"]).is_match("src/a.ts"));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let root = Path::new("/repo");
+        assert_eq!(
+            relative_path(root, Path::new("/repo/src/a.ts")),
+            Some("src/a.ts".to_string())
+        );
+        assert_eq!(relative_path(root, Path::new("/other/a.ts")), None);
+    }
+}
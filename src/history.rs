@@ -0,0 +1,302 @@
+//! Script run history.
+//!
+//! Every script `kn run` runs, including each script of `-p`/`-s` and
+//! `--recursive` runs, is appended to a JSON-lines file under the user's
+//! data directory (`~/.local/share/kn/history.jsonl` on Linux).
+//! The history backs `kn run --last`, `kn history` and the ordering of the
+//! interactive script picker.
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::dotenv::EnvOptions;
+
+/// Entries kept in the store; older ones are dropped when it grows past this
+const MAX_ENTRIES: usize = 1000;
+
+/// Scripts of the last few runs shown first in the picker
+const RECENT_SCRIPTS: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Directory of the package the script belongs to
+    pub project: PathBuf,
+    pub script: String,
+    pub args: Vec<String>,
+    pub duration_ms: u64,
+    pub exit_code: i32,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// How `.env` files were loaded, so `kn run --last` loads them alike
+    #[serde(default)]
+    pub env: EnvOptions,
+    /// Run with `--root`: `project` is the workspace root, and `--last`
+    /// replays the run from anywhere in the workspace
+    #[serde(default)]
+    pub root: bool,
+}
+
+impl HistoryEntry {
+    pub fn new(
+        project: &Path,
+        script: &str,
+        args: &[String],
+        duration: Duration,
+        exit_code: i32,
+    ) -> Self {
+        HistoryEntry {
+            project: project.to_path_buf(),
+            script: script.to_string(),
+            args: args.to_vec(),
+            duration_ms: duration.as_millis() as u64,
+            exit_code,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            env: EnvOptions::default(),
+            root: false,
+        }
+    }
+
+    /// The script with its arguments, as typed after `kn run`
+    pub fn command(&self) -> String {
+        let mut command = self.script.clone();
+        for arg in &self.args {
+            command.push(' ');
+            command.push_str(arg);
+        }
+        command
+    }
+}
+
+/// Per-script timing statistics
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptStats {
+    pub script: String,
+    pub runs: usize,
+    pub failures: usize,
+    pub average_ms: u64,
+    pub last_ms: u64,
+}
+
+/// A history file on disk
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// The history of the current user, if a data directory is available
+    pub fn open() -> Option<Self> {
+        dirs::data_dir().map(|dir| History::at(dir.join("kn").join("history.jsonl")))
+    }
+
+    pub fn at(path: PathBuf) -> Self {
+        History { path }
+    }
+
+    /// All entries, oldest first. Unreadable lines are skipped.
+    pub fn entries(&self) -> Vec<HistoryEntry> {
+        let Ok(contents) = fs::read_to_string(&self.path) else {
+            return Vec::new();
+        };
+        contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()
+    }
+
+    /// Entries of one project, oldest first
+    pub fn project_entries(&self, project: &Path) -> Vec<HistoryEntry> {
+        self.entries()
+            .into_iter()
+            .filter(|e| e.project == project)
+            .collect()
+    }
+
+    pub fn record(&self, entry: &HistoryEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let line = serde_json::to_string(entry)?;
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Failed to open {}", self.path.display()))?;
+        writeln!(file, "{}", line)?;
+        drop(file);
+
+        // Compact once the file is well past the limit, so appends stay cheap
+        let entries = self.entries();
+        if entries.len() > MAX_ENTRIES + MAX_ENTRIES / 10 {
+            self.rewrite(&entries[entries.len() - MAX_ENTRIES..])?;
+        }
+        Ok(())
+    }
+
+    fn rewrite(&self, entries: &[HistoryEntry]) -> Result<()> {
+        let mut contents = String::new();
+        for entry in entries {
+            contents.push_str(&serde_json::to_string(entry)?);
+            contents.push('\n');
+        }
+        let tmp = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Timing statistics per script, most used first
+pub fn script_stats(entries: &[HistoryEntry]) -> Vec<ScriptStats> {
+    let mut stats: IndexMap<&str, ScriptStats> = IndexMap::new();
+    for entry in entries {
+        let stat = stats
+            .entry(entry.script.as_str())
+            .or_insert_with(|| ScriptStats {
+                script: entry.script.clone(),
+                runs: 0,
+                failures: 0,
+                average_ms: 0,
+                last_ms: 0,
+            });
+        stat.average_ms =
+            (stat.average_ms * stat.runs as u64 + entry.duration_ms) / (stat.runs as u64 + 1);
+        stat.runs += 1;
+        stat.last_ms = entry.duration_ms;
+        if entry.exit_code != 0 {
+            stat.failures += 1;
+        }
+    }
+
+    let mut stats: Vec<ScriptStats> = stats.into_values().collect();
+    stats.sort_by_key(|s| std::cmp::Reverse(s.runs));
+    stats
+}
+
+/// Script names ordered for the picker: the most recently run first, then
+/// the most used, then the rest in package.json order
+pub fn rank_scripts(entries: &[HistoryEntry], scripts: &IndexMap<String, String>) -> Vec<String> {
+    let mut ranked: Vec<String> = Vec::new();
+    for entry in entries.iter().rev() {
+        if ranked.len() == RECENT_SCRIPTS {
+            break;
+        }
+        if scripts.contains_key(&entry.script) && !ranked.contains(&entry.script) {
+            ranked.push(entry.script.clone());
+        }
+    }
+
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for entry in entries {
+        *counts.entry(entry.script.as_str()).or_default() += 1;
+    }
+    let mut rest: Vec<&String> = scripts
+        .keys()
+        .filter(|name| !ranked.contains(name))
+        .collect();
+    // Stable sort keeps package.json order among equally used scripts
+    rest.sort_by_key(|name| std::cmp::Reverse(counts.get(name.as_str()).copied().unwrap_or(0)));
+
+    ranked.extend(rest.into_iter().cloned());
+    ranked
+}
+
+/// Rough "5m ago" rendering of a past timestamp
+pub fn time_ago(timestamp: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let secs = now.saturating_sub(timestamp);
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{}m ago", secs / 60),
+        3600..=86399 => format!("{}h ago", secs / 3600),
+        _ => format!("{}d ago", secs / 86400),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cleanup_test_dir(dir: &Path) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).expect("Failed to cleanup test directory");
+        }
+    }
+
+    fn entry(project: &str, script: &str, duration_ms: u64, exit_code: i32) -> HistoryEntry {
+        HistoryEntry {
+            duration_ms,
+            exit_code,
+            ..HistoryEntry::new(Path::new(project), script, &[], Duration::ZERO, 0)
+        }
+    }
+
+    #[test]
+    fn test_record_and_read() {
+        let test_dir = Path::new("/tmp/kn_test_history");
+        cleanup_test_dir(test_dir);
+
+        let history = History::at(test_dir.join("history.jsonl"));
+        assert!(history.entries().is_empty());
+
+        let mut first = entry("/repo/a", "build", 1200, 0);
+        first.args = vec!["--watch".to_string()];
+        history.record(&first).unwrap();
+        history.record(&entry("/repo/b", "test", 300, 1)).unwrap();
+
+        assert_eq!(history.entries().len(), 2);
+        let project = history.project_entries(Path::new("/repo/a"));
+        assert_eq!(project, [first]);
+        assert_eq!(project[0].command(), "build --watch");
+
+        cleanup_test_dir(test_dir);
+    }
+
+    #[test]
+    fn test_rank_and_stats() {
+        let scripts: IndexMap<String, String> = ["dev", "build", "test", "lint", "e2e"]
+            .iter()
+            .map(|name| (name.to_string(), String::new()))
+            .collect();
+        let entries = vec![
+            entry("/p", "lint", 100, 0),
+            entry("/p", "lint", 300, 1),
+            entry("/p", "e2e", 100, 0),
+            entry("/p", "e2e", 100, 0),
+            entry("/p", "e2e", 100, 0),
+            entry("/p", "removed", 100, 0),
+            entry("/p", "test", 100, 0),
+            entry("/p", "build", 100, 0),
+        ];
+
+        assert_eq!(
+            rank_scripts(&entries[..2], &scripts),
+            ["lint", "dev", "build", "test", "e2e"]
+        );
+        assert_eq!(
+            rank_scripts(&entries, &scripts),
+            ["build", "test", "e2e", "lint", "dev"]
+        );
+
+        let stats = script_stats(&entries);
+        assert_eq!(stats[0].script, "e2e");
+        assert_eq!(stats[1].script, "lint");
+        assert_eq!(stats[1].runs, 2);
+        assert_eq!(stats[1].failures, 1);
+        assert_eq!(stats[1].average_ms, 200);
+        assert_eq!(stats[1].last_ms, 300);
+    }
+}
@@ -0,0 +1,168 @@
+//! `.gitignore` and `.ignore` files.
+//!
+//! Rules follow git: a pattern without a slash matches at any depth below the
+//! file's directory, one with a slash is anchored to it, a trailing `/`
+//! matches only directories and a leading `!` re-includes a path. The last
+//! matching rule wins, and `.ignore` is read after `.gitignore` so it can
+//! override it. Patterns use the [`crate::glob`] syntax.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::glob::{relative_path, Glob};
+
+/// Ignore files read in each directory, in increasing precedence
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// The ignore rules of a tree of directories
+#[derive(Debug, Default)]
+pub struct IgnoreFiles {
+    /// Rules of each directory with ignore files, parents before children
+    dirs: Vec<(PathBuf, Vec<Rule>)>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    glob: Glob,
+    negated: bool,
+    dir_only: bool,
+}
+
+impl IgnoreFiles {
+    /// Rules of the directories above `root` up to the enclosing git
+    /// repository's top level, so a package inside a monorepo honours the
+    /// repository's `.gitignore`
+    pub fn above(root: &Path) -> Self {
+        let mut ignores = IgnoreFiles::default();
+        let Some(top) = root.ancestors().find(|dir| dir.join(".git").exists()) else {
+            return ignores;
+        };
+        let mut parents: Vec<&Path> = root
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(top))
+            .collect();
+        parents.reverse();
+        for dir in parents {
+            ignores.load(dir);
+        }
+        ignores
+    }
+
+    /// Read the ignore files of `dir`, replacing rules read from it before
+    pub fn load(&mut self, dir: &Path) {
+        let rules: Vec<Rule> = IGNORE_FILES
+            .iter()
+            .filter_map(|name| fs::read_to_string(dir.join(name)).ok())
+            .flat_map(|contents| parse(&contents))
+            .collect();
+
+        let existing = self.dirs.iter().position(|(d, _)| d == dir);
+        match (existing, rules.is_empty()) {
+            (Some(i), true) => {
+                self.dirs.remove(i);
+            }
+            (Some(i), false) => self.dirs[i].1 = rules,
+            (None, false) => self.dirs.push((dir.to_path_buf(), rules)),
+            (None, true) => {}
+        }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignored = false;
+        for (dir, rules) in &self.dirs {
+            let Some(relative) = relative_path(dir, path) else {
+                continue;
+            };
+            for rule in rules {
+                if (is_dir || !rule.dir_only) && rule.glob.is_match(&relative) {
+                    ignored = !rule.negated;
+                }
+            }
+        }
+        ignored
+    }
+}
+
+fn parse(contents: &str) -> Vec<Rule> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                return None;
+            }
+            let (negated, line) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let (dir_only, line) = match line.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+            let pattern = match line.strip_prefix('/') {
+                Some(anchored) => anchored.to_string(),
+                None if line.contains('/') => line.to_string(),
+                None => format!("**/{}", line),
+            };
+            Some(Rule {
+                glob: Glob::new(&pattern),
+                negated,
+                dir_only,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cleanup_test_dir(dir: &Path) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).expect("Failed to cleanup test directory");
+        }
+    }
+
+    #[test]
+    fn test_gitignore_rules() {
+        let test_dir = Path::new("/tmp/kn_test_ignore_files");
+        cleanup_test_dir(test_dir);
+        fs::create_dir_all(test_dir.join(".git")).unwrap();
+        fs::create_dir_all(test_dir.join("packages/app")).unwrap();
+        fs::write(
+            test_dir.join(".gitignore"),
+            "# comment\n*.log\n!keep.log\n/generated\ntmp/\ndocs/*.html\n",
+        )
+        .unwrap();
+        fs::write(test_dir.join("packages/app/.gitignore"), "*.snap\n").unwrap();
+        fs::write(test_dir.join("packages/app/.ignore"), "!b.snap\n").unwrap();
+
+        let root = test_dir.join("packages/app");
+        let mut ignores = IgnoreFiles::above(&root);
+        ignores.load(&root);
+        let ignored = |path: &str, is_dir| ignores.is_ignored(&test_dir.join(path), is_dir);
+
+        // Unanchored patterns match at any depth, also from a parent directory
+        assert!(ignored("packages/app/src/debug.log", false));
+        assert!(!ignored("packages/app/src/keep.log", false));
+        // Anchored patterns only match below the ignore file's directory
+        assert!(ignored("generated", true));
+        assert!(!ignored("packages/app/generated", true));
+        assert!(ignored("docs/index.html", false));
+        assert!(!ignored("docs/api/index.html", false));
+        // Directory-only patterns
+        assert!(ignored("packages/app/tmp", true));
+        assert!(!ignored("packages/app/tmp", false));
+        // .ignore overrides .gitignore
+        assert!(ignored("packages/app/a.snap", false));
+        assert!(!ignored("packages/app/b.snap", false));
+
+        // Reloading replaces the rules
+        fs::remove_file(root.join(".gitignore")).unwrap();
+        ignores.load(&root);
+        assert!(!ignores.is_ignored(&root.join("a.snap"), false));
+
+        cleanup_test_dir(test_dir);
+    }
+}
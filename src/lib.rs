@@ -0,0 +1,62 @@
+//! kn as a library: package manager detection, command resolution,
+//! package.json parsing and script running.
+//!
+//! ```no_run
+//! use kn::{detect, run_script_fast, DetectOptions};
+//!
+//! let agent = detect(DetectOptions::new()).unwrap_or(kn::Agent::Npm);
+//! let (bin, args) = kn::parse::parse_ni(agent, vec!["react".to_string()], None)?;
+//! run_script_fast("build", &[])?;
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Failures are reported as `anyhow::Error`s; use [`error::exit_code`] to map
+//! them to the code a CLI should exit with.
+
+pub mod agent_cache;
+pub mod agent_install;
+pub mod agents;
+pub mod artifacts;
+pub mod cache;
+pub mod child;
+pub mod command_utils;
+pub mod completions;
+pub mod config;
+pub mod detect;
+pub mod dotenv;
+pub mod error;
+pub mod fuzzy;
+pub mod glob;
+pub mod history;
+pub mod ignore;
+pub mod multi_run;
+pub mod parse;
+pub mod run_log;
+pub mod runner;
+pub mod scan;
+pub mod script_pattern;
+pub mod script_runner;
+pub mod utils;
+pub mod watcher;
+pub mod workspace;
+
+// CLI front-end, not part of the library API
+#[doc(hidden)]
+pub mod cli_parser;
+#[doc(hidden)]
+pub mod command;
+#[doc(hidden)]
+pub mod display;
+#[doc(hidden)]
+pub mod picker;
+#[doc(hidden)]
+pub mod update_checker;
+#[doc(hidden)]
+pub mod version;
+
+pub use agents::Agent;
+pub use command_utils::{run_script_fast, Package};
+pub use detect::detect;
+pub use error::KnError;
+pub use runner::{execa_command, DetectOptions};
+pub use utils::find_and_parse_package_json;
@@ -1,17 +1,6 @@
-mod agents;
-mod cli_parser;
-mod command;
-mod command_utils;
-mod config;
-mod detect;
-mod display;
-mod parse;
-mod runner;
-mod update_checker;
-mod utils;
-mod version;
-
-use cli_parser::Cli;
+use kn::cli_parser::{Cli, Commands};
+use kn::error::find_kn_error;
+use kn::update_checker;
 use tracing_subscriber::{fmt, EnvFilter};
 
 fn init_logging() {
@@ -36,9 +25,6 @@ fn main() {
     // 初始化日志系统
     init_logging();
 
-    // Check for updates in the background (non-blocking)
-    let update_handle = update_checker::check_for_updates();
-
     let cli = match Cli::parse() {
         Ok(cli) => cli,
         Err(e) => {
@@ -47,15 +33,26 @@ fn main() {
         }
     };
 
-    let exit_code = if let Err(e) = cli.execute() {
-        eprintln!("  {} {}", console::style("✖").red().bold(), e);
-        1
-    } else {
-        0
+    // Check for updates in the background (non-blocking). Shells run kn
+    // for completions on every Tab, which must not wait on the network.
+    let update_handle = (!matches!(
+        cli.command,
+        Commands::Completions { .. } | Commands::Complete { .. }
+    ))
+    .then(update_checker::check_for_updates);
+
+    let exit_code = match cli.execute() {
+        Ok(()) => 0,
+        Err(e) => {
+            if !find_kn_error(&e).is_some_and(|k| k.is_silent()) {
+                eprintln!("  {} {}", console::style("✖").red().bold(), e);
+            }
+            kn::error::exit_code(&e)
+        }
     };
 
     // Print update notification after command output is done
-    if let Ok(Some(msg)) = update_handle.join() {
+    if let Some(Ok(Some(msg))) = update_handle.map(|handle| handle.join()) {
         eprint!("{}", msg);
     }
 
@@ -0,0 +1,489 @@
+//! Running several scripts in one go, sequentially or in parallel.
+//!
+//! In parallel mode each task's output is piped and every line is prefixed
+//! with the task label in its own colour. A failing task cancels the others
+//! unless `continue_on_error` is set.
+
+use console::{style, Color};
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::child;
+use crate::display::StyledOutput;
+use crate::error::find_kn_error;
+use crate::script_runner::{run_steps, run_steps_captured, OutputLog, ScriptStep};
+
+const PALETTE: [Color; 6] = [
+    Color::Cyan,
+    Color::Magenta,
+    Color::Yellow,
+    Color::Green,
+    Color::Blue,
+    Color::Red,
+];
+
+/// A labelled unit of work: the steps of one script run
+pub struct Task {
+    pub label: String,
+    pub steps: Vec<ScriptStep>,
+    /// Indices of tasks that must pass first; they must come earlier in the
+    /// task list
+    pub depends_on: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskStatus {
+    Passed,
+    Failed(i32),
+    /// Stopped because another task failed
+    Cancelled,
+    /// Never started because another task or a dependency failed
+    Skipped,
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskResult {
+    pub label: String,
+    pub status: TaskStatus,
+    pub duration: Option<Duration>,
+}
+
+#[derive(Clone, Default)]
+pub struct MultiRunOptions {
+    pub parallel: bool,
+    /// Upper bound on concurrently running tasks (unbounded when `None`)
+    pub max_parallel: Option<usize>,
+    pub continue_on_error: bool,
+    /// Also write the output of every task here
+    pub log: Option<OutputLog>,
+}
+
+/// Run all tasks and report how each one ended, in input order
+pub fn run_tasks(tasks: Vec<Task>, options: &MultiRunOptions) -> Vec<TaskResult> {
+    if options.parallel {
+        run_parallel(tasks, options)
+    } else {
+        run_sequential(tasks, options)
+    }
+}
+
+/// Exit code of the first failed task, if any
+pub fn first_failure(results: &[TaskResult]) -> Option<i32> {
+    results.iter().find_map(|r| match r.status {
+        TaskStatus::Failed(code) => Some(code),
+        _ => None,
+    })
+}
+
+/// Print the duration and exit status of every task as a table
+pub fn print_summary(label_header: &str, results: &[TaskResult]) {
+    let rows: Vec<Vec<String>> = results
+        .iter()
+        .map(|r| {
+            let (icon, exit) = match r.status {
+                TaskStatus::Passed => (style("✔").green(), style("0".to_string()).dim()),
+                TaskStatus::Failed(code) => (style("✖").red(), style(code.to_string()).red()),
+                TaskStatus::Cancelled => {
+                    (style("⊘").yellow(), style("cancelled".to_string()).yellow())
+                }
+                TaskStatus::Skipped => (style("○").dim(), style("skipped".to_string()).dim()),
+            };
+            let duration = r
+                .duration
+                .map(|d| StyledOutput::duration(d.as_secs_f64()))
+                .unwrap_or_else(|| "—".to_string());
+            vec![format!("{} {}", icon, r.label), duration, exit.to_string()]
+        })
+        .collect();
+
+    println!();
+    StyledOutput::titled("Summary");
+    println!();
+    StyledOutput::table(&[label_header, "Duration", "Exit"], &rows);
+    println!();
+}
+
+fn run_sequential(tasks: Vec<Task>, options: &MultiRunOptions) -> Vec<TaskResult> {
+    let mut results = Vec::with_capacity(tasks.len());
+    let mut failed = false;
+
+    for task in tasks {
+        let blocked = task.depends_on.iter().any(|&dep| {
+            results.get(dep).map(|r: &TaskResult| r.status) != Some(TaskStatus::Passed)
+        });
+        if blocked || (failed && !options.continue_on_error) {
+            results.push(TaskResult {
+                label: task.label,
+                status: TaskStatus::Skipped,
+                duration: None,
+            });
+            continue;
+        }
+
+        println!();
+        StyledOutput::separator_with_label(&task.label);
+        println!();
+
+        let start = Instant::now();
+        let result = match &options.log {
+            Some(log) => {
+                let _ = writeln!(lock(log), "── {} ──", task.label);
+                run_steps_captured(task.steps, std::slice::from_ref(log))
+            }
+            None => run_steps(task.steps),
+        };
+        let status = match result {
+            Ok(()) => TaskStatus::Passed,
+            Err(e) => {
+                let kn_error = find_kn_error(&e);
+                if !kn_error.is_some_and(|k| k.is_silent()) {
+                    StyledOutput::error(&e.to_string());
+                }
+                TaskStatus::Failed(kn_error.map(|k| k.exit_code()).unwrap_or(1))
+            }
+        };
+
+        failed |= matches!(status, TaskStatus::Failed(_));
+        results.push(TaskResult {
+            label: task.label,
+            status,
+            duration: Some(start.elapsed()),
+        });
+    }
+
+    results
+}
+
+/// Running children by task index, and whether the run was aborted
+struct Shared {
+    running: Mutex<HashMap<usize, u32>>,
+    aborted: AtomicBool,
+    log: Option<OutputLog>,
+}
+
+impl Shared {
+    fn abort(&self) {
+        let running = self.running.lock().unwrap_or_else(|e| e.into_inner());
+        self.aborted.store(true, Ordering::SeqCst);
+        for pid in running.values() {
+            child::terminate(*pid);
+        }
+    }
+}
+
+fn run_parallel(tasks: Vec<Task>, options: &MultiRunOptions) -> Vec<TaskResult> {
+    let width = tasks.iter().map(|t| t.label.len()).max().unwrap_or(0);
+    let max_parallel = options.max_parallel.unwrap_or(usize::MAX).max(1);
+    let shared = Arc::new(Shared {
+        running: Mutex::new(HashMap::new()),
+        aborted: AtomicBool::new(false),
+        log: options.log.clone(),
+    });
+
+    let mut results: Vec<TaskResult> = tasks
+        .iter()
+        .map(|t| TaskResult {
+            label: t.label.clone(),
+            status: TaskStatus::Skipped,
+            duration: None,
+        })
+        .collect();
+
+    let (tx, rx) = mpsc::channel::<(usize, TaskStatus, Duration)>();
+    let mut done = vec![false; results.len()];
+    let mut pending: VecDeque<(usize, Task)> = tasks.into_iter().enumerate().collect();
+    let mut running = 0;
+
+    loop {
+        skip_blocked(&mut pending, &mut results, &mut done);
+
+        while running < max_parallel && !shared.aborted.load(Ordering::SeqCst) {
+            let ready = pending
+                .iter()
+                .position(|(_, task)| task.depends_on.iter().all(|&dep| done[dep]));
+            let Some((index, task)) = ready.and_then(|position| pending.remove(position)) else {
+                break;
+            };
+
+            let color = PALETTE[index % PALETTE.len()];
+            let prefix = format!(
+                "{} {} ",
+                style(format!("{:<width$}", task.label, width = width))
+                    .fg(color)
+                    .bold(),
+                style("│").dim(),
+            );
+            let shared = Arc::clone(&shared);
+            let tx = tx.clone();
+
+            thread::spawn(move || {
+                let start = Instant::now();
+                let status = run_prefixed(index, task.steps, &prefix, &shared);
+                let _ = tx.send((index, status, start.elapsed()));
+            });
+            running += 1;
+        }
+
+        if running == 0 {
+            break;
+        }
+
+        let Ok((index, status, duration)) = rx.recv() else {
+            break;
+        };
+        running -= 1;
+        done[index] = true;
+        results[index].status = status;
+        results[index].duration = Some(duration);
+
+        if matches!(status, TaskStatus::Failed(_)) && !options.continue_on_error {
+            shared.abort();
+        }
+    }
+
+    results
+}
+
+/// Mark pending tasks whose dependencies did not pass as skipped, and
+/// everything that depends on them in turn
+fn skip_blocked(
+    pending: &mut VecDeque<(usize, Task)>,
+    results: &mut [TaskResult],
+    done: &mut [bool],
+) {
+    loop {
+        let blocked = pending.iter().position(|(_, task)| {
+            task.depends_on
+                .iter()
+                .any(|&dep| done[dep] && results[dep].status != TaskStatus::Passed)
+        });
+        let Some((index, _)) = blocked.and_then(|position| pending.remove(position)) else {
+            return;
+        };
+        results[index].status = TaskStatus::Skipped;
+        done[index] = true;
+    }
+}
+
+fn run_prefixed(index: usize, steps: Vec<ScriptStep>, prefix: &str, shared: &Shared) -> TaskStatus {
+    for mut step in steps {
+        if let Some(display) = &step.display {
+            write_line(
+                &mut io::stdout(),
+                &shared.log,
+                prefix,
+                format!("{}\n", style(format!("$ {}", display)).dim()).as_bytes(),
+            );
+        }
+
+        step.command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        // Piped children would otherwise drop their colours
+        if console::colors_enabled() && std::env::var_os("FORCE_COLOR").is_none() {
+            step.command.env("FORCE_COLOR", "1");
+        }
+
+        let mut spawned = {
+            let mut running = shared.running.lock().unwrap_or_else(|e| e.into_inner());
+            if shared.aborted.load(Ordering::SeqCst) {
+                return TaskStatus::Cancelled;
+            }
+            match child::spawn(&mut step.command) {
+                Ok(spawned) => {
+                    running.insert(index, spawned.id());
+                    spawned
+                }
+                Err(e) => {
+                    let message = format!("Failed to execute script '{}': {}\n", step.event, e);
+                    write_line(&mut io::stderr(), &shared.log, prefix, message.as_bytes());
+                    return TaskStatus::Failed(1);
+                }
+            }
+        };
+
+        let pumps: Vec<JoinHandle<()>> = [
+            spawned
+                .take_stdout()
+                .map(|out| pump(out, prefix.to_string(), shared.log.clone(), false)),
+            spawned
+                .take_stderr()
+                .map(|err| pump(err, prefix.to_string(), shared.log.clone(), true)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let status = spawned.wait();
+        for handle in pumps {
+            let _ = handle.join();
+        }
+        shared
+            .running
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&index);
+
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(_) if shared.aborted.load(Ordering::SeqCst) => return TaskStatus::Cancelled,
+            Ok(status) => return TaskStatus::Failed(child::exit_code(&status)),
+            Err(_) => return TaskStatus::Failed(1),
+        }
+    }
+
+    TaskStatus::Passed
+}
+
+/// Copy `reader` line by line to stdout/stderr, prefixing every line
+fn pump<R: Read + Send + 'static>(
+    reader: R,
+    prefix: String,
+    log: Option<OutputLog>,
+    stderr: bool,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    if !line.ends_with(b"\n") {
+                        line.push(b'\n');
+                    }
+                    if stderr {
+                        write_line(&mut io::stderr(), &log, &prefix, &line);
+                    } else {
+                        write_line(&mut io::stdout(), &log, &prefix, &line);
+                    }
+                }
+            }
+        }
+    })
+}
+
+fn write_line<W: Write>(out: &mut W, log: &Option<OutputLog>, prefix: &str, line: &[u8]) {
+    // A single write keeps lines from different tasks from interleaving
+    let mut buffer = Vec::with_capacity(prefix.len() + line.len());
+    buffer.extend_from_slice(prefix.as_bytes());
+    buffer.extend_from_slice(line);
+    let _ = out.write_all(&buffer);
+    let _ = out.flush();
+    if let Some(log) = log {
+        let _ = lock(log).write_all(&buffer);
+    }
+}
+
+fn lock(log: &OutputLog) -> MutexGuard<'_, dyn Write + Send + 'static> {
+    log.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::script_runner::shell_command;
+
+    fn task(label: &str, script: &str) -> Task {
+        Task {
+            label: label.to_string(),
+            steps: vec![ScriptStep {
+                event: label.to_string(),
+                display: None,
+                command: shell_command(script),
+            }],
+            depends_on: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parallel_failure_cancels_others() {
+        let options = MultiRunOptions {
+            parallel: true,
+            max_parallel: Some(2),
+            continue_on_error: false,
+            ..Default::default()
+        };
+        let results = run_tasks(
+            vec![
+                task("slow", "sleep 30"),
+                task("fail", "exit 3"),
+                task("never", "true"),
+            ],
+            &options,
+        );
+
+        assert_eq!(results[0].status, TaskStatus::Cancelled);
+        assert_eq!(results[1].status, TaskStatus::Failed(3));
+        assert_eq!(results[2].status, TaskStatus::Skipped);
+        assert_eq!(first_failure(&results), Some(3));
+    }
+
+    #[test]
+    fn test_continue_on_error() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let options = MultiRunOptions {
+            parallel: false,
+            max_parallel: None,
+            continue_on_error: true,
+            log: Some(log.clone() as OutputLog),
+        };
+        let results = run_tasks(
+            vec![task("fail", "echo broken; exit 2"), task("ok", "echo fine")],
+            &options,
+        );
+
+        assert_eq!(results[0].status, TaskStatus::Failed(2));
+        assert_eq!(results[1].status, TaskStatus::Passed);
+        let log = String::from_utf8(log.lock().unwrap().clone()).unwrap();
+        assert!(log.starts_with("── fail ──\n"));
+        assert!(log.contains("broken\n"));
+        assert!(log.contains("fine\n"));
+    }
+
+    #[test]
+    fn test_dependencies_run_first() {
+        let dir = std::path::Path::new("/tmp/kn_test_multi_run_deps");
+        let _ = std::fs::remove_dir_all(dir);
+        std::fs::create_dir_all(dir).unwrap();
+        let log = dir.join("log");
+        let append = |label: &str| format!("sleep 0.1; echo {} >> '{}'", label, log.display());
+
+        let options = MultiRunOptions {
+            parallel: true,
+            max_parallel: None,
+            continue_on_error: true,
+            ..Default::default()
+        };
+        let mut app = task("app", &append("app"));
+        app.depends_on = vec![0, 1];
+        let mut docs = task("docs", "true");
+        docs.depends_on = vec![2];
+        let results = run_tasks(
+            vec![
+                task("core", &append("core")),
+                task("broken", "exit 1"),
+                task("utils", &append("utils")),
+                docs,
+                app,
+            ],
+            &options,
+        );
+
+        assert_eq!(results[0].status, TaskStatus::Passed);
+        assert_eq!(results[1].status, TaskStatus::Failed(1));
+        assert_eq!(results[3].status, TaskStatus::Passed);
+        assert_eq!(results[4].status, TaskStatus::Skipped);
+        let log = std::fs::read_to_string(&log).unwrap();
+        assert!(!log.contains("app"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
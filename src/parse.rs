@@ -1,4 +1,7 @@
+use anyhow::Result;
+
 use crate::agents::Agent;
+use crate::error::KnError;
 use crate::runner::RunnerContext;
 
 /// Parse function for install commands (ni)
@@ -7,17 +10,17 @@ pub fn parse_ni(
     agent: Agent,
     args: Vec<String>,
     _ctx: Option<RunnerContext>,
-) -> (String, Vec<String>) {
+) -> Result<(String, Vec<String>)> {
     let mut cmd_args = args;
 
     // Handle frozen install flag
     if let Some(index) = cmd_args.iter().position(|arg| arg == "--frozen") {
         cmd_args.remove(index);
         let frozen = agent.frozen_args();
-        return (
+        return Ok((
             agent.name().to_string(),
             frozen.into_iter().map(|s| s.to_string()).collect(),
-        );
+        ));
     }
 
     // Handle global install
@@ -30,22 +33,22 @@ pub fn parse_ni(
                 .collect();
             let mut result = vec!["global".to_string(), agent.add_cmd().to_string()];
             result.extend(filtered);
-            return (agent.name().to_string(), result);
+            return Ok((agent.name().to_string(), result));
         } else {
             // npm install -g <packages> / pnpm add -g / bun add -g
             let mut result = vec![agent.add_cmd().to_string()];
             result.extend(cmd_args);
-            return (agent.name().to_string(), result);
+            return Ok((agent.name().to_string(), result));
         }
     }
 
     // Regular install
     if cmd_args.is_empty() {
-        (agent.name().to_string(), vec!["install".to_string()])
+        Ok((agent.name().to_string(), vec!["install".to_string()]))
     } else {
         let mut result = vec![agent.add_cmd().to_string()];
         result.extend(cmd_args);
-        (agent.name().to_string(), result)
+        Ok((agent.name().to_string(), result))
     }
 }
 
@@ -55,7 +58,7 @@ pub fn parse_nun(
     agent: Agent,
     args: Vec<String>,
     _ctx: Option<RunnerContext>,
-) -> (String, Vec<String>) {
+) -> Result<(String, Vec<String>)> {
     let cmd_args = args;
 
     // Handle global uninstall
@@ -67,17 +70,17 @@ pub fn parse_nun(
                 .collect();
             let mut result = vec!["global".to_string(), agent.remove_cmd().to_string()];
             result.extend(filtered);
-            return (agent.name().to_string(), result);
+            return Ok((agent.name().to_string(), result));
         } else {
             let mut result = vec![agent.remove_cmd().to_string()];
             result.extend(cmd_args);
-            return (agent.name().to_string(), result);
+            return Ok((agent.name().to_string(), result));
         }
     }
 
     let mut result = vec![agent.remove_cmd().to_string()];
     result.extend(cmd_args);
-    (agent.name().to_string(), result)
+    Ok((agent.name().to_string(), result))
 }
 
 /// Parse function for execute commands (nlx)
@@ -86,17 +89,17 @@ pub fn parse_nlx(
     agent: Agent,
     args: Vec<String>,
     _ctx: Option<RunnerContext>,
-) -> (String, Vec<String>) {
+) -> Result<(String, Vec<String>)> {
     if args.is_empty() {
-        return (agent.exec_binary().to_string(), args);
+        return Ok((agent.exec_binary().to_string(), args));
     }
 
     if agent.exec_uses_dlx() {
         let mut dlx_args = vec!["dlx".to_string()];
         dlx_args.extend(args);
-        (agent.name().to_string(), dlx_args)
+        Ok((agent.name().to_string(), dlx_args))
     } else {
-        (agent.exec_binary().to_string(), args)
+        Ok((agent.exec_binary().to_string(), args))
     }
 }
 
@@ -106,7 +109,7 @@ pub fn parse_nu(
     agent: Agent,
     args: Vec<String>,
     _ctx: Option<RunnerContext>,
-) -> (String, Vec<String>) {
+) -> Result<(String, Vec<String>)> {
     let mut cmd_args = args;
 
     // Handle interactive upgrade
@@ -117,24 +120,23 @@ pub fn parse_nu(
         cmd_args.remove(index);
 
         if !agent.supports_interactive_upgrade() {
-            eprintln!(
-                "{} {} does not support interactive upgrades",
-                console::style("✗ Error:").red().bold(),
-                agent
-            );
-            std::process::exit(1);
+            return Err(KnError::Unsupported {
+                agent,
+                operation: "interactive upgrades",
+            }
+            .into());
         }
 
         let base = agent.interactive_upgrade_args();
         let mut result: Vec<String> = base.into_iter().map(|s| s.to_string()).collect();
         result.extend(cmd_args);
-        return (agent.name().to_string(), result);
+        return Ok((agent.name().to_string(), result));
     }
 
     // Regular upgrade
     let mut result = vec![agent.upgrade_cmd().to_string()];
     result.extend(cmd_args);
-    (agent.name().to_string(), result)
+    Ok((agent.name().to_string(), result))
 }
 
 #[cfg(test)]
@@ -144,7 +146,7 @@ mod tests {
 
     #[test]
     fn test_parse_ni() {
-        let (cmd, args) = parse_ni(Agent::Npm, vec!["lodash".to_string()], None);
+        let (cmd, args) = parse_ni(Agent::Npm, vec!["lodash".to_string()], None).unwrap();
         assert_eq!(cmd, "npm");
         assert_eq!(args, vec!["install", "lodash"]);
 
@@ -152,20 +154,21 @@ mod tests {
             Agent::Npm,
             vec!["-g".to_string(), "typescript".to_string()],
             None,
-        );
+        )
+        .unwrap();
         assert_eq!(cmd, "npm");
         assert!(args.contains(&"-g".to_string()));
         assert!(args.contains(&"typescript".to_string()));
 
-        let (cmd, args) = parse_ni(Agent::Npm, vec!["--frozen".to_string()], None);
+        let (cmd, args) = parse_ni(Agent::Npm, vec!["--frozen".to_string()], None).unwrap();
         assert_eq!(cmd, "npm");
         assert_eq!(args, vec!["ci"]);
 
-        let (cmd, args) = parse_ni(Agent::Yarn, vec!["react".to_string()], None);
+        let (cmd, args) = parse_ni(Agent::Yarn, vec!["react".to_string()], None).unwrap();
         assert_eq!(cmd, "yarn");
         assert_eq!(args, vec!["add", "react"]);
 
-        let (cmd, args) = parse_ni(Agent::Bun, vec!["express".to_string()], None);
+        let (cmd, args) = parse_ni(Agent::Bun, vec!["express".to_string()], None).unwrap();
         assert_eq!(cmd, "bun");
         assert_eq!(args, vec!["add", "express"]);
     }
@@ -176,7 +179,8 @@ mod tests {
             Agent::Npm,
             vec!["cowsay".to_string(), "Hello".to_string()],
             None,
-        );
+        )
+        .unwrap();
         assert_eq!(cmd, "npx");
         assert_eq!(args, vec!["cowsay", "Hello"]);
 
@@ -184,7 +188,8 @@ mod tests {
             Agent::Yarn,
             vec!["cowsay".to_string(), "Hello".to_string()],
             None,
-        );
+        )
+        .unwrap();
         assert_eq!(cmd, "yarn");
         assert_eq!(args, vec!["dlx", "cowsay", "Hello"]);
 
@@ -192,18 +197,19 @@ mod tests {
             Agent::Bun,
             vec!["cowsay".to_string(), "Hello".to_string()],
             None,
-        );
+        )
+        .unwrap();
         assert_eq!(cmd, "bunx");
         assert_eq!(args, vec!["cowsay", "Hello"]);
     }
 
     #[test]
     fn test_parse_nun() {
-        let (cmd, args) = parse_nun(Agent::Npm, vec!["lodash".to_string()], None);
+        let (cmd, args) = parse_nun(Agent::Npm, vec!["lodash".to_string()], None).unwrap();
         assert_eq!(cmd, "npm");
         assert_eq!(args, vec!["uninstall", "lodash"]);
 
-        let (cmd, args) = parse_nun(Agent::Yarn, vec!["react".to_string()], None);
+        let (cmd, args) = parse_nun(Agent::Yarn, vec!["react".to_string()], None).unwrap();
         assert_eq!(cmd, "yarn");
         assert_eq!(args, vec!["remove", "react"]);
 
@@ -211,7 +217,8 @@ mod tests {
             Agent::Pnpm,
             vec!["-g".to_string(), "typescript".to_string()],
             None,
-        );
+        )
+        .unwrap();
         assert_eq!(cmd, "pnpm");
         assert!(args.contains(&"-g".to_string()));
         assert!(args.contains(&"typescript".to_string()));
@@ -219,16 +226,25 @@ mod tests {
 
     #[test]
     fn test_parse_nu() {
-        let (cmd, args) = parse_nu(Agent::Npm, vec!["lodash".to_string()], None);
+        let (cmd, args) = parse_nu(Agent::Npm, vec!["lodash".to_string()], None).unwrap();
         assert_eq!(cmd, "npm");
         assert_eq!(args, vec!["update", "lodash"]);
 
-        let (cmd, args) = parse_nu(Agent::Yarn, vec!["react".to_string()], None);
+        let (cmd, args) = parse_nu(Agent::Yarn, vec!["react".to_string()], None).unwrap();
         assert_eq!(cmd, "yarn");
         assert_eq!(args, vec!["upgrade", "react"]);
 
-        let (cmd, args) = parse_nu(Agent::YarnBerry, vec!["react".to_string()], None);
+        let (cmd, args) = parse_nu(Agent::YarnBerry, vec!["react".to_string()], None).unwrap();
         assert_eq!(cmd, "yarn");
         assert_eq!(args, vec!["up", "react"]);
+
+        let err = parse_nu(Agent::Npm, vec!["-i".to_string()], None).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<KnError>(),
+            Some(&KnError::Unsupported {
+                agent: Agent::Npm,
+                operation: "interactive upgrades",
+            })
+        );
     }
 }
@@ -0,0 +1,320 @@
+//! Interactive type-to-filter picker.
+//!
+//! Items are filtered with [`crate::fuzzy`] as the user types, ranked by
+//! score, and shown with their matches highlighted. Tab marks several items;
+//! they are returned in the order they were marked.
+
+use console::{pad_str, style, truncate_str, Alignment, Key, Term};
+use std::io;
+
+use crate::fuzzy::{fuzzy_match, FuzzyMatch};
+
+/// Rows of the list shown at once
+const VISIBLE_ROWS: usize = 10;
+
+/// Lines of the preview pane for long commands
+const PREVIEW_LINES: usize = 3;
+
+/// Matches in the detail column count for less than matches in the name
+const DETAIL_PENALTY: i64 = 20;
+
+pub struct PickerItem {
+    pub name: String,
+    pub detail: String,
+}
+
+/// An item that matched the query, with what to highlight
+struct Candidate {
+    index: usize,
+    score: i64,
+    name_positions: Vec<usize>,
+    detail_positions: Vec<usize>,
+}
+
+/// Let the user pick items. Returns `None` when cancelled, otherwise the
+/// marked items (or the highlighted one when none were marked).
+pub fn pick(prompt: &str, items: &[PickerItem]) -> io::Result<Option<Vec<usize>>> {
+    let term = Term::stdout();
+    let mut query = String::new();
+    let mut cursor = 0;
+    let mut offset = 0;
+    let mut marked: Vec<usize> = Vec::new();
+    let mut drawn = 0;
+
+    let _cursor = HiddenCursor::new(&term)?;
+    let result = loop {
+        let candidates = filter(items, &query);
+        cursor = cursor.min(candidates.len().saturating_sub(1));
+        if cursor < offset {
+            offset = cursor;
+        } else if cursor >= offset + VISIBLE_ROWS {
+            offset = cursor + 1 - VISIBLE_ROWS;
+        }
+
+        let width = term.size().1 as usize;
+        let lines = render(
+            prompt,
+            items,
+            &candidates,
+            &query,
+            cursor,
+            offset,
+            &marked,
+            width,
+        );
+        term.clear_last_lines(drawn)?;
+        for line in &lines {
+            term.write_line(line)?;
+        }
+        drawn = lines.len();
+
+        match term.read_key()? {
+            Key::Escape | Key::CtrlC => break None,
+            Key::Enter => {
+                if !marked.is_empty() {
+                    break Some(marked);
+                }
+                if let Some(candidate) = candidates.get(cursor) {
+                    break Some(vec![candidate.index]);
+                }
+            }
+            Key::ArrowUp | Key::BackTab => {
+                cursor = cursor
+                    .checked_sub(1)
+                    .unwrap_or(candidates.len().saturating_sub(1));
+            }
+            Key::ArrowDown => {
+                cursor = if cursor + 1 < candidates.len() {
+                    cursor + 1
+                } else {
+                    0
+                };
+            }
+            Key::Tab => {
+                if let Some(candidate) = candidates.get(cursor) {
+                    match marked.iter().position(|&i| i == candidate.index) {
+                        Some(position) => {
+                            marked.remove(position);
+                        }
+                        None => marked.push(candidate.index),
+                    }
+                    cursor = if cursor + 1 < candidates.len() {
+                        cursor + 1
+                    } else {
+                        cursor
+                    };
+                }
+            }
+            Key::Backspace => {
+                query.pop();
+                cursor = 0;
+            }
+            Key::Char(c) if !c.is_control() => {
+                query.push(c);
+                cursor = 0;
+            }
+            _ => {}
+        }
+    };
+
+    term.clear_last_lines(drawn)?;
+    Ok(result)
+}
+
+/// Keeps the terminal cursor hidden until dropped, so an error while
+/// picking does not leave it hidden
+struct HiddenCursor<'a>(&'a Term);
+
+impl<'a> HiddenCursor<'a> {
+    fn new(term: &'a Term) -> io::Result<Self> {
+        term.hide_cursor()?;
+        Ok(HiddenCursor(term))
+    }
+}
+
+impl Drop for HiddenCursor<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.show_cursor();
+    }
+}
+
+/// Items matching `query`, best first; ties keep the original order
+fn filter(items: &[PickerItem], query: &str) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| {
+            let name = fuzzy_match(query, &item.name);
+            let detail = fuzzy_match(query, &item.detail).map(|m| FuzzyMatch {
+                score: m.score - DETAIL_PENALTY,
+                ..m
+            });
+            match (name, detail) {
+                (Some(name), Some(detail)) if detail.score > name.score => Some(Candidate {
+                    index,
+                    score: detail.score,
+                    name_positions: Vec::new(),
+                    detail_positions: detail.positions,
+                }),
+                (Some(name), _) => Some(Candidate {
+                    index,
+                    score: name.score,
+                    name_positions: name.positions,
+                    detail_positions: Vec::new(),
+                }),
+                (None, Some(detail)) => Some(Candidate {
+                    index,
+                    score: detail.score,
+                    name_positions: Vec::new(),
+                    detail_positions: detail.positions,
+                }),
+                (None, None) => None,
+            }
+        })
+        .collect();
+    candidates.sort_by_key(|c| std::cmp::Reverse(c.score));
+    candidates
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render(
+    prompt: &str,
+    items: &[PickerItem],
+    candidates: &[Candidate],
+    query: &str,
+    cursor: usize,
+    offset: usize,
+    marked: &[usize],
+    width: usize,
+) -> Vec<String> {
+    let mut lines = vec![format!(
+        "  {} {} {}{}  {}",
+        style("?").cyan().bold(),
+        style(prompt).bold(),
+        query,
+        style("█").dim(),
+        style(format!("{}/{}", candidates.len(), items.len())).dim(),
+    )];
+
+    let name_width = items
+        .iter()
+        .map(|item| item.name.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(32);
+
+    if candidates.is_empty() {
+        lines.push(format!("    {}", style("No matching scripts").dim()));
+    }
+    for (row, candidate) in candidates
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(VISIBLE_ROWS)
+    {
+        let item = &items[candidate.index];
+        let pointer = if row == cursor {
+            style("❯").cyan().bold().to_string()
+        } else {
+            " ".to_string()
+        };
+        let mark = match marked.iter().position(|&i| i == candidate.index) {
+            Some(order) => style(format!("{}", order + 1)).green().bold().to_string(),
+            None => style("○").dim().to_string(),
+        };
+        let name = highlight(&item.name, &candidate.name_positions, |s| s.to_string());
+        let name = pad_str(&name, name_width, Alignment::Left, None);
+        let detail = highlight(&item.detail, &candidate.detail_positions, |s| {
+            style(s).dim().to_string()
+        });
+        let line = format!("  {} {} {}  {}", pointer, mark, name, detail);
+        lines.push(truncate_str(&line, width.saturating_sub(1), "…").to_string());
+    }
+
+    // Full command of the highlighted item
+    lines.push(String::new());
+    if let Some(candidate) = candidates.get(cursor) {
+        let detail: Vec<char> = items[candidate.index].detail.chars().collect();
+        let chunk = width.saturating_sub(8).max(20);
+        for (i, part) in detail.chunks(chunk).take(PREVIEW_LINES).enumerate() {
+            let prefix = if i == 0 { "$" } else { " " };
+            let mut text: String = part.iter().collect();
+            if i == PREVIEW_LINES - 1 && detail.len() > chunk * PREVIEW_LINES {
+                text.push('…');
+            }
+            lines.push(format!("    {} {}", style(prefix).dim(), style(text).dim()));
+        }
+    }
+    lines.push(format!(
+        "  {}",
+        style("↑↓ move · tab mark · enter run · esc cancel").dim()
+    ));
+    lines
+}
+
+/// Style matched characters, and the rest with `plain`
+fn highlight(text: &str, positions: &[usize], plain: impl Fn(&str) -> String) -> String {
+    let mut out = String::new();
+    for (i, c) in text.chars().enumerate() {
+        let c = c.to_string();
+        if positions.contains(&i) {
+            out.push_str(&style(c).cyan().bold().to_string());
+        } else {
+            out.push_str(&plain(&c));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items(entries: &[(&str, &str)]) -> Vec<PickerItem> {
+        entries
+            .iter()
+            .map(|(name, detail)| PickerItem {
+                name: name.to_string(),
+                detail: detail.to_string(),
+            })
+            .collect()
+    }
+
+    fn indices(candidates: &[Candidate]) -> Vec<usize> {
+        candidates.iter().map(|c| c.index).collect()
+    }
+
+    #[test]
+    fn test_filter_ranking() {
+        let items = items(&[
+            ("lint", "eslint . && tsc --noEmit"),
+            ("test", "vitest run"),
+            ("build", "tsc -p ."),
+            ("dev", "vite"),
+            ("vite", "vite build --watch"),
+        ]);
+
+        // An empty query keeps every item in order
+        assert_eq!(indices(&filter(&items, "")), [0, 1, 2, 3, 4]);
+
+        // Items matching in neither column are dropped
+        assert!(filter(&items, "xyz").is_empty());
+
+        // A match in the name beats an equally good one in the detail,
+        // which beats a worse one
+        let candidates = filter(&items, "vite");
+        assert_eq!(indices(&candidates), [4, 3, 1]);
+        assert_eq!(candidates[0].name_positions, [0, 1, 2, 3]);
+        assert_eq!(candidates[1].detail_positions, [0, 1, 2, 3]);
+        assert!(candidates[1].name_positions.is_empty());
+
+        // Detail-only matches are kept, highlighted in the detail
+        let candidates = filter(&items, "tsc");
+        assert_eq!(indices(&candidates), [2, 0]);
+        assert!(candidates.iter().all(|c| c.name_positions.is_empty()));
+
+        let candidates = filter(&items, "bui");
+        assert_eq!(indices(&candidates), [2, 4]);
+        assert_eq!(candidates[0].name_positions, [0, 1, 2]);
+    }
+}
@@ -0,0 +1,313 @@
+//! Log files of script runs (`kn run --log <file>` or the `logs_dir` config).
+//!
+//! A log starts with a header describing the run, followed by everything the
+//! scripts write to stdout and stderr with ANSI escape codes removed, as it
+//! arrives. A trailer with the duration and exit code ends it once the run
+//! is over.
+
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Longest unfinished escape sequence held back for the next write
+const MAX_PENDING: usize = 4096;
+
+/// What the header of a log records
+pub struct LogHeader {
+    /// The kn command line
+    pub command: String,
+    pub agent: String,
+    pub cwd: PathBuf,
+    pub started: SystemTime,
+}
+
+/// A log file being written while its run goes on. Writes strip ANSI escape
+/// codes, including ones split across writes.
+pub struct RunLog {
+    path: PathBuf,
+    file: File,
+    /// Start of an escape sequence the last write ended in
+    pending: Vec<u8>,
+    /// The first write that failed, reported by [`RunLog::finish`]
+    error: Option<io::Error>,
+}
+
+/// The log file for a run: `explicit` when given, otherwise a new
+/// timestamped file in `logs_dir` (relative to `base`)
+pub fn log_path(
+    explicit: Option<&Path>,
+    logs_dir: Option<&Path>,
+    base: &Path,
+    name: &str,
+    started: SystemTime,
+) -> Option<PathBuf> {
+    if let Some(path) = explicit {
+        return Some(path.to_path_buf());
+    }
+    logs_dir.map(|dir| base.join(dir).join(file_name(name, started)))
+}
+
+/// `build-20261018-142501.log`; characters unsafe in file names become `_`
+pub fn file_name(name: &str, started: SystemTime) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "-_.+@".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let (year, month, day, hour, minute, second) = utc(started);
+    format!(
+        "{}-{:04}{:02}{:02}-{:02}{:02}{:02}.log",
+        name, year, month, day, hour, minute, second
+    )
+}
+
+impl RunLog {
+    /// Create the log file at `path` and write its header
+    pub fn create(path: &Path, header: &LogHeader) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+
+        let (year, month, day, hour, minute, second) = utc(header.started);
+        let mut contents = Vec::new();
+        writeln!(contents, "# {}", header.command)?;
+        writeln!(contents, "# agent:    {}", header.agent)?;
+        writeln!(contents, "# cwd:      {}", header.cwd.display())?;
+        writeln!(
+            contents,
+            "# started:  {:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            year, month, day, hour, minute, second
+        )?;
+        writeln!(contents)?;
+
+        let mut file =
+            File::create(path).with_context(|| format!("Failed to write {}", path.display()))?;
+        file.write_all(&contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(RunLog {
+            path: path.to_path_buf(),
+            file,
+            pending: Vec::new(),
+            error: None,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append the trailer once the run is over, failing when any write to
+    /// the file did
+    pub fn finish(&mut self, duration: Duration, exit_code: i32) -> Result<()> {
+        let context = format!("Failed to write {}", self.path.display());
+        if let Some(e) = self.error.take() {
+            return Err(anyhow::Error::new(e).context(context));
+        }
+
+        // An escape sequence cut off by the end of the output is dropped
+        self.pending.clear();
+        let mut trailer = Vec::new();
+        writeln!(trailer)?;
+        writeln!(trailer, "# duration: {:.2}s", duration.as_secs_f64())?;
+        writeln!(trailer, "# exit:     {}", exit_code)?;
+        self.file
+            .write_all(&trailer)
+            .and_then(|()| self.file.flush())
+            .context(context)
+    }
+}
+
+impl Write for RunLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        let (mut text, consumed) = strip_complete(&self.pending);
+        if self.pending.len() - consumed > MAX_PENDING {
+            // Too long to be an escape sequence: kept as it is
+            text.extend_from_slice(&self.pending[consumed..]);
+            self.pending.clear();
+        } else {
+            self.pending.drain(..consumed);
+        }
+        match self.file.write_all(&text) {
+            Ok(()) => Ok(buf.len()),
+            Err(e) => {
+                let kind = e.kind();
+                self.error.get_or_insert(e);
+                Err(kind.into())
+            }
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Remove ANSI escape sequences (colours, cursor movement, hyperlinks)
+pub fn strip_ansi(input: &[u8]) -> Vec<u8> {
+    strip_complete(input).0
+}
+
+/// `input` without ANSI escape sequences, and how much of it was consumed:
+/// an escape sequence `input` ends in the middle of is left out
+fn strip_complete(input: &[u8]) -> (Vec<u8>, usize) {
+    let mut out = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] != 0x1b {
+            out.push(input[i]);
+            i += 1;
+            continue;
+        }
+        let start = i;
+        match input.get(i + 1) {
+            // CSI: parameters, then a final byte in @..~
+            Some(b'[') => {
+                i += 2;
+                while i < input.len() && !(0x40..=0x7e).contains(&input[i]) {
+                    i += 1;
+                }
+                if i == input.len() {
+                    return (out, start);
+                }
+                i += 1;
+            }
+            // OSC: ends with BEL or ESC \
+            Some(b']') => {
+                i += 2;
+                loop {
+                    match input.get(i) {
+                        Some(0x07) => {
+                            i += 1;
+                            break;
+                        }
+                        Some(0x1b) => match input.get(i + 1) {
+                            Some(b'\\') => {
+                                i += 2;
+                                break;
+                            }
+                            Some(_) => i += 1,
+                            None => return (out, start),
+                        },
+                        Some(_) => i += 1,
+                        None => return (out, start),
+                    }
+                }
+            }
+            Some(_) => i += 2,
+            None => return (out, start),
+        }
+    }
+    (out, input.len())
+}
+
+/// Calendar date and time of `time` in UTC
+fn utc(time: SystemTime) -> (i64, u32, u32, u32, u32, u32) {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let (days, rest) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // Civil date from days since the epoch (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    (
+        year,
+        month,
+        day,
+        (rest / 3600) as u32,
+        (rest % 3600 / 60) as u32,
+        (rest % 60) as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cleanup_test_dir(dir: &Path) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).expect("Failed to cleanup test directory");
+        }
+    }
+
+    #[test]
+    fn test_strip_ansi_and_file_name() {
+        assert_eq!(
+            strip_ansi(b"\x1b[1;32mok\x1b[0m \x1b]8;;https://x\x07link\x1b]8;;\x1b\\ done\x1b[2K"),
+            b"ok link done"
+        );
+        assert_eq!(strip_ansi(b"plain\r\n"), b"plain\r\n");
+
+        // 2024-02-29 13:05:09 UTC
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_211_909);
+        assert_eq!(
+            file_name("build:prod", time),
+            "build_prod-20240229-130509.log"
+        );
+        assert_eq!(utc(UNIX_EPOCH), (1970, 1, 1, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_write_log() {
+        let test_dir = Path::new("/tmp/kn_test_run_log");
+        cleanup_test_dir(test_dir);
+
+        let started = SystemTime::now();
+        let path = log_path(None, Some(Path::new("logs")), test_dir, "test", started)
+            .expect("logs_dir should give a path");
+        assert!(path.starts_with(test_dir.join("logs")));
+        assert_eq!(
+            log_path(Some(Path::new("out.log")), None, test_dir, "test", started),
+            Some(PathBuf::from("out.log"))
+        );
+        assert_eq!(log_path(None, None, test_dir, "test", started), None);
+
+        let header = LogHeader {
+            command: "kn run test".to_string(),
+            agent: "pnpm".to_string(),
+            cwd: PathBuf::from("/repo"),
+            started,
+        };
+        let mut log = RunLog::create(&path, &header).expect("Failed to create log");
+
+        // The header is there before any output
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.starts_with("# kn run test\n# agent:    pnpm\n# cwd:      /repo\n"));
+        assert!(!contents.contains("# exit:"));
+
+        // Escape sequences split across writes are stripped too
+        for chunk in [
+            &b"\x1b[31mfai"[..],
+            b"led\x1b",
+            b"[0m\n\x1b]8;;https:",
+            b"//x\x07",
+        ] {
+            log.write_all(chunk).unwrap();
+        }
+        log.finish(Duration::from_millis(1500), 1)
+            .expect("Failed to finish log");
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.ends_with("Z\n\nfailed\n\n# duration: 1.50s\n# exit:     1\n"));
+
+        cleanup_test_dir(test_dir);
+    }
+}
@@ -2,20 +2,25 @@ use anyhow::{Context, Result};
 use inquire::Select;
 use std::env;
 use std::path::{Path, PathBuf};
-use std::process::{self, Command, Stdio};
+use std::process::{Command, Stdio};
 use tracing::{debug, info, warn};
 
+use crate::agent_install::ensure_agent;
 use crate::agents::Agent;
 use crate::agents::AGENT_MAP;
+use crate::child;
 use crate::config::{get_default_agent, get_global_agent, DefaultAgent};
 use crate::detect::detect;
 use crate::display::StyledOutput;
+use crate::error::KnError;
 
 #[derive(Clone)]
 pub struct DetectOptions {
     pub cwd: PathBuf,
     pub auto_install: bool,
     pub programmatic: bool,
+    /// Extra environment for the spawned command (from `.env` files)
+    pub env: Vec<(String, String)>,
 }
 impl Default for DetectOptions {
     fn default() -> Self {
@@ -23,6 +28,7 @@ impl Default for DetectOptions {
             cwd: env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
             auto_install: false,
             programmatic: false,
+            env: Vec::new(),
         }
     }
 }
@@ -34,6 +40,10 @@ impl DetectOptions {
         self.auto_install = auto_install;
         self
     }
+    pub fn with_env(mut self, env: Vec<(String, String)>) -> Self {
+        self.env = env;
+        self
+    }
 }
 
 #[allow(dead_code)]
@@ -43,8 +53,11 @@ pub struct RunnerContext {
     pub cwd: PathBuf,
 }
 
-pub type Runner =
-    fn(agent: Agent, args: Vec<String>, ctx: Option<RunnerContext>) -> (String, Vec<String>);
+pub type Runner = fn(
+    agent: Agent,
+    args: Vec<String>,
+    ctx: Option<RunnerContext>,
+) -> Result<(String, Vec<String>)>;
 
 pub fn run_cli(func: Runner, options: Option<DetectOptions>, args: Vec<String>) -> Result<()> {
     let mut options = options.unwrap_or_default();
@@ -79,7 +92,7 @@ pub fn run(func: Runner, args: Vec<String>, options: &mut DetectOptions) -> Resu
     let command = get_cli_command(func, args, options.clone())?;
 
     if let Some((agent, cmd_args)) = command {
-        execa_command(&agent, cmd_args)?;
+        execa_command_with_env(&agent, cmd_args, &options.env)?;
     }
 
     Ok(())
@@ -95,7 +108,9 @@ fn get_cli_command(
     let global = "-g".to_string();
     if args.contains(&global) {
         info!("Using global agent for command");
-        return Ok(Some(func(get_global_agent(), args, None)));
+        let agent = get_global_agent();
+        ensure_agent(agent, &options)?;
+        return func(agent, args, None).map(Some);
     }
 
     let mut agent = if let Some(v) = detect(options.clone()) {
@@ -122,39 +137,50 @@ fn get_cli_command(
                     return Ok(None);
                 }
             }
-            Err(_) => {
-                process::exit(1);
-            }
+            Err(_) => return Err(KnError::Cancelled.into()),
         }
     }
 
     let runner_ctx = RunnerContext {
         programmatic: options.programmatic,
         has_lock: true,
-        cwd: options.cwd,
+        cwd: options.cwd.clone(),
     };
 
-    match agent {
-        DefaultAgent::Agent(agent) => Ok(Some(func(agent, args, Some(runner_ctx)))),
-        DefaultAgent::Prompt => Ok(Some(func(Agent::Npm, args, Some(runner_ctx)))),
-    }
+    let agent = match agent {
+        DefaultAgent::Agent(agent) => agent,
+        DefaultAgent::Prompt => Agent::Npm,
+    };
+    ensure_agent(agent, &options)?;
+
+    func(agent, args, Some(runner_ctx)).map(Some)
 }
 
-/// Execute a command and propagate its exit code
+/// Execute a command, failing with `KnError::Exit` when it exits unsuccessfully
 pub fn execa_command(agent: &str, args: Vec<String>) -> Result<()> {
+    execa_command_with_env(agent, args, &[])
+}
+
+/// Like [`execa_command`], with extra environment variables for the child
+pub fn execa_command_with_env(
+    agent: &str,
+    args: Vec<String>,
+    env: &[(String, String)],
+) -> Result<()> {
     info!("Executing command: {} {:?}", agent, args);
 
-    let status = Command::new(agent)
-        .args(&args)
+    let mut cmd = Command::new(agent);
+    cmd.args(&args)
+        .envs(env.iter().map(|(k, v)| (k, v)))
         .stdout(Stdio::inherit())
         .stderr(Stdio::inherit())
-        .stdin(Stdio::inherit())
-        .status()
+        .stdin(Stdio::inherit());
+
+    let status = child::run(&mut cmd)
         .with_context(|| format!("Failed to execute command: {} {:?}", agent, args))?;
 
     if !status.success() {
-        let code = status.code().unwrap_or(1);
-        process::exit(code);
+        return Err(KnError::Exit(child::exit_code(&status)).into());
     }
 
     Ok(())
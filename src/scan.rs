@@ -0,0 +1,230 @@
+//! Finding `node_modules` directories across a directory tree.
+//!
+//! The walk never descends into `node_modules`, `.git` or symlinks. Sizes and
+//! project ages are then measured on a pool of threads, one top-level entry
+//! of a directory at a time, so a single huge install does not keep the
+//! others waiting. [`dir_sizes`] measures other directories, such as package
+//! manager caches, the same way.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::SystemTime;
+
+/// Directories the walk does not enter
+const SKIPPED_DIRS: [&str; 2] = [".git", "node_modules"];
+
+/// A `node_modules` directory and the project it belongs to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Install {
+    /// The `node_modules` directory
+    pub path: PathBuf,
+    /// Size of its contents in bytes
+    pub size: u64,
+    /// Newest change in the project around it, its `node_modules` aside
+    pub modified: SystemTime,
+}
+
+impl Install {
+    /// The directory holding the `node_modules`
+    pub fn project(&self) -> &Path {
+        self.path.parent().unwrap_or(&self.path)
+    }
+}
+
+/// A unit of work for the thread pool, tagged with its install's index
+enum Job {
+    Size(usize, PathBuf),
+    Modified(usize, PathBuf),
+}
+
+/// Every `node_modules` below `root`, sorted by path
+pub fn find_node_modules(root: &Path) -> Vec<Install> {
+    let mut found = Vec::new();
+    collect(root, &mut found);
+    found.sort();
+
+    let mut jobs = Vec::new();
+    for (index, dir) in found.iter().enumerate() {
+        if let Ok(entries) = fs::read_dir(dir) {
+            jobs.extend(entries.flatten().map(|e| Job::Size(index, e.path())));
+        }
+        if let Some(project) = dir.parent() {
+            jobs.push(Job::Modified(index, project.to_path_buf()));
+        }
+    }
+
+    let installs = Mutex::new(
+        found
+            .into_iter()
+            .map(|path| Install {
+                path,
+                size: 0,
+                modified: SystemTime::UNIX_EPOCH,
+            })
+            .collect::<Vec<_>>(),
+    );
+    run_parallel(&jobs, |job| match job {
+        Job::Size(index, path) => {
+            let size = tree_size(path);
+            installs.lock().unwrap()[*index].size += size;
+        }
+        Job::Modified(index, project) => {
+            let modified = newest_change(project);
+            installs.lock().unwrap()[*index].modified = modified;
+        }
+    });
+
+    installs.into_inner().unwrap()
+}
+
+/// Sizes of the contents of `dirs` in bytes, 0 for ones that do not exist
+pub fn dir_sizes(dirs: &[PathBuf]) -> Vec<u64> {
+    let jobs: Vec<(usize, PathBuf)> = dirs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, dir)| Some((index, fs::read_dir(dir).ok()?)))
+        .flat_map(|(index, entries)| entries.flatten().map(move |e| (index, e.path())))
+        .collect();
+
+    let sizes = Mutex::new(vec![0u64; dirs.len()]);
+    run_parallel(&jobs, |(index, path)| {
+        let size = tree_size(path);
+        sizes.lock().unwrap()[*index] += size;
+    });
+    sizes.into_inner().unwrap()
+}
+
+/// Run `job` on each of `jobs`, spread over one thread per core
+fn run_parallel<T: Sync>(jobs: &[T], job: impl Fn(&T) + Sync) {
+    let next = AtomicUsize::new(0);
+    let workers = thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+        .min(jobs.len());
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                while let Some(item) = jobs.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    job(item);
+                }
+            });
+        }
+    });
+}
+
+fn collect(dir: &Path, found: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        // Links are not followed, so nothing is found twice
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        if !file_type.is_dir() {
+            continue;
+        }
+        let name = entry.file_name();
+        if name == "node_modules" {
+            found.push(entry.path());
+        } else if !SKIPPED_DIRS.contains(&name.to_string_lossy().as_ref()) {
+            collect(&entry.path(), found);
+        }
+    }
+}
+
+/// Size of the files below `path`, skipping what cannot be read
+fn tree_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| entries.flatten().map(|e| tree_size(&e.path())).sum())
+        .unwrap_or(0)
+}
+
+/// Newest modification time of `dir` and what is below it, outside of
+/// `node_modules` and `.git`
+fn newest_change(dir: &Path) -> SystemTime {
+    let mut newest = fs::symlink_metadata(dir)
+        .and_then(|m| m.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let Ok(entries) = fs::read_dir(dir) else {
+        return newest;
+    };
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let modified = if metadata.is_dir() {
+            if SKIPPED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                continue;
+            }
+            newest_change(&entry.path())
+        } else {
+            metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH)
+        };
+        newest = newest.max(modified);
+    }
+    newest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn cleanup_test_dir(dir: &Path) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).expect("Failed to cleanup test directory");
+        }
+    }
+
+    #[test]
+    fn test_find_node_modules() {
+        let test_dir = Path::new("/tmp/kn_test_scan");
+        cleanup_test_dir(test_dir);
+        for dir in [
+            "app/node_modules/react/node_modules/dep",
+            "app/src",
+            "work/lib/node_modules/.bin",
+            "work/.git/node_modules",
+            "empty",
+        ] {
+            fs::create_dir_all(test_dir.join(dir)).unwrap();
+        }
+        fs::write(test_dir.join("app/node_modules/react/index.js"), "12345").unwrap();
+        fs::write(
+            test_dir.join("app/node_modules/react/node_modules/dep/a.js"),
+            "123",
+        )
+        .unwrap();
+        fs::write(test_dir.join("work/lib/node_modules/.bin/tool"), "12").unwrap();
+        fs::write(test_dir.join("app/src/index.js"), "x").unwrap();
+
+        let installs = find_node_modules(test_dir);
+        let paths: Vec<&Path> = installs.iter().map(|i| i.path.as_path()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                test_dir.join("app/node_modules"),
+                test_dir.join("work/lib/node_modules"),
+            ]
+        );
+        assert_eq!(installs[0].size, 8);
+        assert_eq!(installs[1].size, 2);
+        assert_eq!(installs[0].project(), test_dir.join("app"));
+
+        let recent = SystemTime::now() - Duration::from_secs(3600);
+        assert!(installs.iter().all(|i| i.modified > recent));
+
+        cleanup_test_dir(test_dir);
+    }
+}
@@ -0,0 +1,76 @@
+//! Script name patterns for `kn run`.
+//!
+//! Follows the `npm-run-all` conventions: `*` and `?` stay within one
+//! `:`-separated segment, `**` crosses segments, and `{a,b}` lists
+//! alternatives. Matching is that of [`crate::glob`] with `:` for `/`.
+//! Matches keep package.json order.
+
+use indexmap::IndexMap;
+
+use crate::glob::Glob;
+
+/// Whether `input` should be expanded instead of looked up verbatim
+pub fn is_pattern(input: &str) -> bool {
+    input.contains(['*', '?', '{'])
+}
+
+/// Script names matching `pattern`, in package.json order
+pub fn expand(pattern: &str, scripts: &IndexMap<String, String>) -> Vec<String> {
+    let glob = Glob::with_separator(pattern, ':');
+    scripts
+        .keys()
+        .filter(|name| glob.is_match(name))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(names: &[&str]) -> IndexMap<String, String> {
+        names
+            .iter()
+            .map(|name| (name.to_string(), format!("echo {}", name)))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_patterns() {
+        let scripts = scripts(&[
+            "build:js",
+            "test:unit",
+            "build:css",
+            "test:e2e",
+            "build:css:min",
+            "test:lint",
+            "build",
+        ]);
+
+        assert_eq!(expand("build:*", &scripts), ["build:js", "build:css"]);
+        assert_eq!(
+            expand("build:**", &scripts),
+            ["build:js", "build:css", "build:css:min"]
+        );
+        assert_eq!(
+            expand("test:{unit,e2e}", &scripts),
+            ["test:unit", "test:e2e"]
+        );
+        assert_eq!(expand("build:?s", &scripts), ["build:js"]);
+        assert_eq!(
+            expand("{build,test}:{js,lint}", &scripts),
+            ["build:js", "test:lint"]
+        );
+        assert!(expand("deploy:*", &scripts).is_empty());
+        assert_eq!(expand("**:min", &scripts), ["build:css:min"]);
+
+        // Many globstars on a deep name must not try every split
+        let deep = vec!["a"; 40].join(":");
+        let deep: IndexMap<String, String> = [(deep, String::new())].into_iter().collect();
+        assert!(expand("**:a:**:a:**:a:**:a:**:b", &deep).is_empty());
+
+        assert!(is_pattern("build:*"));
+        assert!(is_pattern("test:{a,b}"));
+        assert!(!is_pattern("build:prod"));
+    }
+}
@@ -0,0 +1,673 @@
+//! Native package.json script execution.
+//!
+//! Runs script commands through the system shell the way `npm run` does,
+//! without paying for a Node.js startup: `node_modules/.bin` of the package
+//! and every ancestor is prepended to `PATH`, the `npm_*` environment is set,
+//! and `pre<name>`/`post<name>` hooks run around the script.
+
+use anyhow::{Context, Result};
+use console::style;
+use serde_json::Value;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::agents::Agent;
+use crate::child;
+use crate::error::KnError;
+use crate::utils::find_in_path;
+use crate::version::CURRENT_VERSION;
+
+/// One command of a script run: a lifecycle hook, the script itself, or a
+/// delegated `<agent> run` invocation
+pub struct ScriptStep {
+    /// Lifecycle event name (`prebuild`, `build`, ...)
+    pub event: String,
+    /// Command text to show before running, `None` when the package manager
+    /// prints its own banner
+    pub display: Option<String>,
+    pub command: Command,
+}
+
+/// Run `script_name` from the package.json at `package_json_path`, including
+/// its `pre`/`post` hooks. `args` are appended to the main script only.
+pub fn run_native(
+    package_json_path: &Path,
+    script_name: &str,
+    args: &[String],
+    agent: Agent,
+) -> Result<()> {
+    run_steps(native_steps(package_json_path, script_name, args, agent)?)
+}
+
+/// Run steps in order in the foreground, stopping at the first failure
+pub fn run_steps(steps: Vec<ScriptStep>) -> Result<()> {
+    for mut step in steps {
+        if let Some(display) = &step.display {
+            println!(
+                "  {} {}  {}",
+                style("▸").cyan(),
+                style(&step.event).bold(),
+                style(display).dim(),
+            );
+            println!();
+        }
+
+        let status = child::run(&mut step.command)
+            .with_context(|| format!("Failed to execute script '{}'", step.event))?;
+
+        if !status.success() {
+            return Err(KnError::Exit(child::exit_code(&status)).into());
+        }
+    }
+    Ok(())
+}
+
+/// Where the output of a run is copied as it arrives, such as a log file
+pub type OutputLog = Arc<Mutex<dyn Write + Send>>;
+
+/// Like [`run_steps`], but the children's output is also written to each of
+/// `logs` as it arrives. Their stdout and stderr are pipes, so colours are
+/// forced on when kn's own output is coloured. They still get the terminal,
+/// so scripts can read from it.
+pub fn run_steps_captured(steps: Vec<ScriptStep>, logs: &[OutputLog]) -> Result<()> {
+    for mut step in steps {
+        if let Some(display) = &step.display {
+            println!(
+                "  {} {}  {}",
+                style("▸").cyan(),
+                style(&step.event).bold(),
+                style(display).dim(),
+            );
+            println!();
+        }
+
+        if console::colors_enabled() && env::var_os("FORCE_COLOR").is_none() {
+            step.command.env("FORCE_COLOR", "1");
+        }
+        step.command.stdout(Stdio::piped()).stderr(Stdio::piped());
+        let mut spawned = child::spawn_foreground(&mut step.command)
+            .with_context(|| format!("Failed to execute script '{}'", step.event))?;
+
+        let pumps: Vec<JoinHandle<()>> = [
+            spawned
+                .take_stdout()
+                .map(|out| tee(out, io::stdout, logs.to_vec())),
+            spawned
+                .take_stderr()
+                .map(|err| tee(err, io::stderr, logs.to_vec())),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+
+        let status = spawned.wait();
+        for handle in pumps {
+            let _ = handle.join();
+        }
+        let status =
+            status.with_context(|| format!("Failed to execute script '{}'", step.event))?;
+        if !status.success() {
+            return Err(KnError::Exit(child::exit_code(&status)).into());
+        }
+    }
+    Ok(())
+}
+
+/// Steps running on a background thread, each as a background child, so
+/// they can be stopped at any point (`kn watch`)
+pub struct RunningSteps {
+    stopped: Arc<AtomicBool>,
+    /// Pid of the step's child while one runs
+    current: Arc<Mutex<Option<u32>>>,
+    thread: JoinHandle<Result<()>>,
+}
+
+/// Start running steps in order on a background thread, stopping at the
+/// first failure
+pub fn spawn_steps(steps: Vec<ScriptStep>) -> RunningSteps {
+    let stopped = Arc::new(AtomicBool::new(false));
+    let current = Arc::new(Mutex::new(None));
+
+    let thread = {
+        let stopped = Arc::clone(&stopped);
+        let current = Arc::clone(&current);
+        thread::spawn(move || {
+            for mut step in steps {
+                let spawned = {
+                    // Checked under the lock so no step starts after `stop`
+                    let mut current = current.lock().unwrap_or_else(|e| e.into_inner());
+                    if stopped.load(Ordering::SeqCst) {
+                        return Ok(());
+                    }
+                    if let Some(display) = &step.display {
+                        println!(
+                            "  {} {}  {}",
+                            style("▸").cyan(),
+                            style(&step.event).bold(),
+                            style(display).dim(),
+                        );
+                        println!();
+                    }
+                    let spawned = child::spawn(&mut step.command)
+                        .with_context(|| format!("Failed to execute script '{}'", step.event))?;
+                    *current = Some(spawned.id());
+                    spawned
+                };
+
+                let status = spawned.wait();
+                *current.lock().unwrap_or_else(|e| e.into_inner()) = None;
+                let status =
+                    status.with_context(|| format!("Failed to execute script '{}'", step.event))?;
+                if !status.success() {
+                    return Err(KnError::Exit(child::exit_code(&status)).into());
+                }
+            }
+            Ok(())
+        })
+    };
+
+    RunningSteps {
+        stopped,
+        current,
+        thread,
+    }
+}
+
+impl RunningSteps {
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Wait for the steps to finish
+    pub fn wait(self) -> Result<()> {
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(_) => anyhow::bail!("Script runner thread panicked"),
+        }
+    }
+
+    /// Terminate the running step's process group, which is killed if it
+    /// outlives the grace period, and skip the remaining steps
+    pub fn stop(self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        if let Some(pid) = *self.current.lock().unwrap_or_else(|e| e.into_inner()) {
+            child::terminate(pid);
+        }
+        let _ = self.wait();
+    }
+}
+
+/// Copy `reader` to `out` and `logs` as it arrives
+fn tee<R, W>(mut reader: R, out: fn() -> W, logs: Vec<OutputLog>) -> JoinHandle<()>
+where
+    R: Read + Send + 'static,
+    W: Write + 'static,
+{
+    thread::spawn(move || {
+        let mut buffer = [0u8; 8192];
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    let mut out = out();
+                    let _ = out.write_all(&buffer[..n]);
+                    let _ = out.flush();
+                    for log in &logs {
+                        let _ = log
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .write_all(&buffer[..n]);
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Resolve the lifecycle steps of a native script run
+pub fn native_steps(
+    package_json_path: &Path,
+    script_name: &str,
+    args: &[String],
+    agent: Agent,
+) -> Result<Vec<ScriptStep>> {
+    let contents = fs::read_to_string(package_json_path)
+        .with_context(|| format!("Failed to read {}", package_json_path.display()))?;
+    let json: Value = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse {}", package_json_path.display()))?;
+
+    let script = |name: &str| json["scripts"][name].as_str().map(str::to_string);
+    let command = script(script_name)
+        .with_context(|| format!("Script '{}' not found in package.json", script_name))?;
+
+    let package_dir = package_json_path.parent().unwrap_or_else(|| Path::new("."));
+    let base_env = script_env(package_json_path, &json, agent);
+
+    let hooks = runs_hooks(agent, package_dir);
+    let hook = |prefix: &str| {
+        let event = format!("{}{}", prefix, script_name);
+        script(&event)
+            .filter(|_| hooks)
+            .map(|command| (event, command))
+    };
+    let lifecycle: Vec<(String, String)> = hook("pre")
+        .into_iter()
+        .chain([(script_name.to_string(), append_args(&command, args))])
+        .chain(hook("post"))
+        .collect();
+
+    Ok(lifecycle
+        .into_iter()
+        .map(|(event, command)| {
+            let mut cmd = shell_command(&command);
+            cmd.current_dir(package_dir)
+                .envs(base_env.iter().map(|(k, v)| (k, v)))
+                .env("npm_lifecycle_event", &event)
+                .env("npm_lifecycle_script", &command);
+            ScriptStep {
+                event,
+                display: Some(command),
+                command: cmd,
+            }
+        })
+        .collect())
+}
+
+/// Whether `agent` runs the `pre<name>`/`post<name>` hooks of scripts in
+/// `package_dir`: yarn berry never does, and pnpm 7+ only with
+/// `enable-pre-post-scripts`
+fn runs_hooks(agent: Agent, package_dir: &Path) -> bool {
+    match agent {
+        Agent::YarnBerry => false,
+        Agent::Pnpm => pnpm_pre_post_scripts(package_dir),
+        Agent::Npm | Agent::Yarn | Agent::Pnpm6 | Agent::Bun => true,
+    }
+}
+
+/// pnpm's `enable-pre-post-scripts` setting, from the environment or the
+/// nearest `.npmrc`, then the user's
+fn pnpm_pre_post_scripts(package_dir: &Path) -> bool {
+    const KEY: &str = "enable-pre-post-scripts";
+    if let Ok(value) = env::var("npm_config_enable_pre_post_scripts") {
+        return value == "true";
+    }
+    package_dir
+        .ancestors()
+        .map(|dir| dir.join(".npmrc"))
+        .chain(dirs::home_dir().map(|home| home.join(".npmrc")))
+        .find_map(|npmrc| npmrc_value(&npmrc, KEY))
+        .is_some_and(|value| value == "true")
+}
+
+/// The value of `key` in the `.npmrc` at `path`
+fn npmrc_value(path: &Path, key: &str) -> Option<String> {
+    let contents = fs::read_to_string(path).ok()?;
+    contents.lines().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        (name.trim() == key).then(|| value.trim().to_string())
+    })
+}
+
+/// Build a command that runs `script` through the platform shell
+pub fn shell_command(script: &str) -> Command {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        let shell = env::var("ComSpec").unwrap_or_else(|_| "cmd.exe".to_string());
+        let mut cmd = Command::new(shell);
+        cmd.args(["/d", "/s", "/c"])
+            .raw_arg(format!("\"{}\"", script));
+        cmd
+    }
+    #[cfg(not(windows))]
+    {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+}
+
+/// Append extra arguments to a script command, quoted for the shell
+pub fn append_args(command: &str, args: &[String]) -> String {
+    let mut full = command.to_string();
+    for arg in args {
+        full.push(' ');
+        full.push_str(&quote_arg(arg));
+    }
+    full
+}
+
+/// Quote a single argument so the shell passes it through verbatim
+pub fn quote_arg(arg: &str) -> String {
+    if cfg!(windows) {
+        quote_cmd_arg(arg)
+    } else {
+        quote_sh_arg(arg)
+    }
+}
+
+fn quote_sh_arg(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:@,+%".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Quote for `cmd`, which expands `%VAR%` even between quotes: the quotes
+/// and every character `cmd` treats specially are escaped with `^`, as
+/// cross-spawn does
+fn quote_cmd_arg(arg: &str) -> String {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "-_./=:@+".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_safe) {
+        return arg.to_string();
+    }
+    let quoted = format!("\"{}\"", arg.replace('"', "\"\""));
+    let mut escaped = String::with_capacity(quoted.len() * 2);
+    for c in quoted.chars() {
+        if "()[]%!^\"`<>&|;, *?".contains(c) {
+            escaped.push('^');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// `node_modules/.bin` of `package_dir` and all of its ancestors, nearest first
+pub fn bin_paths(package_dir: &Path) -> Vec<PathBuf> {
+    package_dir
+        .ancestors()
+        .map(|dir| dir.join("node_modules").join(".bin"))
+        .collect()
+}
+
+/// The `npm_package_*` variables npm derives from package.json
+pub fn package_env(json: &Value) -> Vec<(String, String)> {
+    let mut env = Vec::new();
+    for key in ["name", "version", "config", "engines", "bin"] {
+        flatten_env(&json[key], &format!("npm_package_{}", key), &mut env);
+    }
+    env
+}
+
+fn flatten_env(value: &Value, key: &str, env: &mut Vec<(String, String)>) {
+    match value {
+        Value::Null => {}
+        Value::Bool(false) => env.push((key.to_string(), String::new())),
+        Value::Bool(true) => env.push((key.to_string(), "true".to_string())),
+        Value::Number(n) => env.push((key.to_string(), n.to_string())),
+        Value::String(s) => env.push((key.to_string(), s.clone())),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten_env(item, &format!("{}_{}", key, i), env);
+            }
+        }
+        Value::Object(map) => {
+            for (k, v) in map {
+                flatten_env(v, &format!("{}_{}", key, k), env);
+            }
+        }
+    }
+}
+
+/// Environment shared by every lifecycle step of a script run
+fn script_env(package_json_path: &Path, json: &Value, agent: Agent) -> Vec<(String, OsString)> {
+    let package_dir = package_json_path.parent().unwrap_or_else(|| Path::new("."));
+
+    let mut paths = bin_paths(package_dir);
+    if let Some(current) = env::var_os("PATH") {
+        paths.extend(env::split_paths(&current));
+    }
+    let path = env::join_paths(paths).unwrap_or_else(|_| env::var_os("PATH").unwrap_or_default());
+
+    let mut vars: Vec<(String, OsString)> = vec![
+        ("PATH".to_string(), path),
+        ("npm_command".to_string(), "run-script".into()),
+        (
+            "npm_package_json".to_string(),
+            package_json_path.as_os_str().to_owned(),
+        ),
+        (
+            "npm_execpath".to_string(),
+            env::current_exe().unwrap_or_default().into_os_string(),
+        ),
+        (
+            "npm_config_user_agent".to_string(),
+            user_agent(agent).into(),
+        ),
+    ];
+
+    if env::var_os("INIT_CWD").is_none() {
+        if let Ok(cwd) = env::current_dir() {
+            vars.push(("INIT_CWD".to_string(), cwd.into_os_string()));
+        }
+    }
+
+    if let Some(node) = find_in_path("node") {
+        vars.push((
+            "npm_node_execpath".to_string(),
+            node.clone().into_os_string(),
+        ));
+        vars.push(("NODE".to_string(), node.into_os_string()));
+    }
+
+    vars.extend(package_env(json).into_iter().map(|(k, v)| (k, v.into())));
+    vars
+}
+
+/// `npm_config_user_agent`, led by the detected agent so tools like
+/// `only-allow` still recognise the package manager
+fn user_agent(agent: Agent) -> String {
+    let os = match env::consts::OS {
+        "macos" => "darwin",
+        "windows" => "win32",
+        other => other,
+    };
+    let arch = match env::consts::ARCH {
+        "x86_64" => "x64",
+        "aarch64" => "arm64",
+        "x86" => "ia32",
+        other => other,
+    };
+    format!("{} kn/{} {} {}", agent.name(), CURRENT_VERSION, os, arch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cleanup_test_dir(dir: &Path) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).expect("Failed to cleanup test directory");
+        }
+    }
+
+    #[test]
+    fn test_quote_arg() {
+        assert_eq!(quote_sh_arg("--watch"), "--watch");
+        assert_eq!(quote_sh_arg("src/index.ts"), "src/index.ts");
+        assert_eq!(quote_sh_arg(""), "''");
+        assert_eq!(quote_sh_arg("hello world"), "'hello world'");
+        assert_eq!(quote_sh_arg("it's"), "'it'\\''s'");
+        assert_eq!(quote_sh_arg("100%"), "100%");
+
+        assert_eq!(quote_cmd_arg("--watch"), "--watch");
+        assert_eq!(quote_cmd_arg("%PATH%"), r#"^"^%PATH^%^""#);
+        assert_eq!(quote_cmd_arg("a b"), r#"^"a^ b^""#);
+        assert_eq!(quote_cmd_arg(r#"say "hi""#), r#"^"say^ ^"^"hi^"^"^""#);
+        assert_eq!(
+            append_args("jest", &["-t".to_string(), "my test".to_string()]),
+            "jest -t 'my test'"
+        );
+    }
+
+    #[test]
+    fn test_bin_paths_nearest_first() {
+        let paths = bin_paths(Path::new("/repo/packages/app"));
+        assert_eq!(paths[0], Path::new("/repo/packages/app/node_modules/.bin"));
+        assert_eq!(paths[1], Path::new("/repo/packages/node_modules/.bin"));
+        assert_eq!(paths[2], Path::new("/repo/node_modules/.bin"));
+        assert_eq!(paths.last().unwrap(), Path::new("/node_modules/.bin"));
+    }
+
+    #[test]
+    fn test_package_env() {
+        let json: Value = serde_json::from_str(
+            r#"{
+"name": "app",
+"version": "1.2.3",
+"config": { "port": 8080, "debug": false },
+"engines": { "node": ">=18" },
+"bin": ["cli.js"],
+"scripts": { "start": "node ." }
+}"#,
+        )
+        .unwrap();
+
+        let env = package_env(&json);
+        let get = |k: &str| {
+            env.iter()
+                .find(|(key, _)| key == k)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(get("npm_package_name"), Some("app"));
+        assert_eq!(get("npm_package_version"), Some("1.2.3"));
+        assert_eq!(get("npm_package_config_port"), Some("8080"));
+        assert_eq!(get("npm_package_config_debug"), Some(""));
+        assert_eq!(get("npm_package_engines_node"), Some(">=18"));
+        assert_eq!(get("npm_package_bin_0"), Some("cli.js"));
+        assert_eq!(get("npm_package_scripts_start"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stop_running_steps() {
+        let step = |event: &str, script: &str| ScriptStep {
+            event: event.to_string(),
+            display: None,
+            command: shell_command(script),
+        };
+
+        let running = spawn_steps(vec![step("dev", "sleep 30"), step("postdev", "exit 4")]);
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(!running.is_finished());
+        let started = std::time::Instant::now();
+        running.stop();
+        assert!(started.elapsed() < child::GRACE_PERIOD);
+
+        let failed = spawn_steps(vec![step("build", "exit 3"), step("postbuild", "sleep 30")]);
+        let err = failed.wait().unwrap_err();
+        assert_eq!(crate::error::exit_code(&err), 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_captured_steps_read_stdin() {
+        let test_dir = Path::new("/tmp/kn_test_captured_stdin");
+        cleanup_test_dir(test_dir);
+        fs::create_dir_all(test_dir).expect("Failed to create test directory");
+        fs::write(test_dir.join("input"), "hello\n").unwrap();
+
+        let mut command = shell_command("read answer; echo \"got $answer\"");
+        command.stdin(fs::File::open(test_dir.join("input")).unwrap());
+        let step = ScriptStep {
+            event: "ask".to_string(),
+            display: None,
+            command,
+        };
+        let log = Arc::new(Mutex::new(Vec::new()));
+        run_steps_captured(vec![step], &[log.clone() as OutputLog]).expect("Failed to run step");
+        assert_eq!(*log.lock().unwrap(), b"got hello\n");
+
+        cleanup_test_dir(test_dir);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_native_with_hooks() {
+        let test_dir = Path::new("/tmp/kn_test_native_runner");
+        cleanup_test_dir(test_dir);
+        fs::create_dir_all(test_dir.join("node_modules/.bin"))
+            .expect("Failed to create test directory");
+
+        let tool = test_dir.join("node_modules/.bin/kn-test-tool");
+        fs::write(
+            &tool,
+            "#!/bin/sh\necho \"tool $npm_lifecycle_event $*\" >> log\n",
+        )
+        .expect("Failed to write tool");
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        fs::write(
+            test_dir.join("package.json"),
+            r#"{
+"name": "native-test",
+"version": "1.0.0",
+"scripts": {
+"prebuild": "echo \"pre $npm_package_name\" >> log",
+"build": "kn-test-tool",
+"postbuild": "echo post >> log",
+"fail": "exit 4"
+}
+}"#,
+        )
+        .expect("Failed to write package.json");
+
+        let package_json = test_dir.join("package.json");
+        run_native(&package_json, "build", &["a b".to_string()], Agent::Npm)
+            .expect("Script should succeed");
+
+        let log = fs::read_to_string(test_dir.join("log")).expect("Failed to read log");
+        assert_eq!(log, "pre native-test\ntool build a b\npost\n");
+
+        let err = run_native(&package_json, "fail", &[], Agent::Npm).unwrap_err();
+        assert_eq!(err.downcast_ref::<KnError>(), Some(&KnError::Exit(4)));
+
+        cleanup_test_dir(test_dir);
+    }
+
+    #[test]
+    fn test_hooks_per_agent() {
+        let test_dir = Path::new("/tmp/kn_test_native_hooks");
+        cleanup_test_dir(test_dir);
+        fs::create_dir_all(test_dir.join("app")).expect("Failed to create test directory");
+        let package_json = test_dir.join("app/package.json");
+        fs::write(
+            &package_json,
+            r#"{"scripts": {"prebuild": "echo pre", "build": "echo build", "postbuild": "echo post"}}"#,
+        )
+        .unwrap();
+
+        let events = |agent: Agent| -> Vec<String> {
+            native_steps(&package_json, "build", &[], agent)
+                .expect("Failed to resolve steps")
+                .into_iter()
+                .map(|step| step.event)
+                .collect()
+        };
+        let all = ["prebuild", "build", "postbuild"];
+        for agent in [Agent::Npm, Agent::Yarn, Agent::Pnpm6, Agent::Bun] {
+            assert_eq!(events(agent), all, "{} runs hooks", agent);
+        }
+        assert_eq!(events(Agent::YarnBerry), ["build"]);
+        if env::var_os("npm_config_enable_pre_post_scripts").is_none() {
+            // pnpm 7+ runs them once enabled, here in the workspace root's .npmrc
+            fs::write(test_dir.join(".npmrc"), "enable-pre-post-scripts=false\n").unwrap();
+            assert_eq!(events(Agent::Pnpm), ["build"]);
+            fs::write(test_dir.join(".npmrc"), "enable-pre-post-scripts = true\n").unwrap();
+            assert_eq!(events(Agent::Pnpm), all);
+        }
+
+        cleanup_test_dir(test_dir);
+    }
+}
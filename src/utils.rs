@@ -11,11 +11,11 @@ pub fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     let len2 = s2.chars().count();
     let mut matrix = vec![vec![0; len2 + 1]; len1 + 1];
 
-    for i in 0..=len1 {
-        matrix[i][0] = i;
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[0] = i;
     }
-    for j in 0..=len2 {
-        matrix[0][j] = j;
+    for (j, cell) in matrix[0].iter_mut().enumerate() {
+        *cell = j;
     }
 
     for (i, c1) in s1.chars().enumerate() {
@@ -31,6 +31,14 @@ pub fn levenshtein_distance(s1: &str, s2: &str) -> usize {
     matrix[len1][len2]
 }
 
+/// Whether kn can prompt the user (attached to a terminal and not in CI)
+pub fn is_interactive() -> bool {
+    use std::io::IsTerminal;
+    std::env::var_os("CI").is_none()
+        && std::io::stdin().is_terminal()
+        && std::io::stdout().is_terminal()
+}
+
 /// Find a file by walking up the directory tree, returns the file path
 pub fn find_up(filename: &str, cwd: &Path) -> Option<PathBuf> {
     let mut current = cwd.to_path_buf();
@@ -46,6 +54,34 @@ pub fn find_up(filename: &str, cwd: &Path) -> Option<PathBuf> {
     None
 }
 
+/// Locate an executable on PATH (honouring PATHEXT-style extensions on Windows)
+pub fn find_in_path(binary: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    let extensions: &[&str] = if cfg!(windows) {
+        &["", ".exe", ".cmd", ".bat"]
+    } else {
+        &[""]
+    };
+
+    std::env::split_paths(&paths).find_map(|dir| {
+        extensions
+            .iter()
+            .map(|ext| dir.join(format!("{}{}", binary, ext)))
+            .find(|candidate| is_executable(candidate))
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
 /// Find package.json in the directory tree and parse it
 pub fn find_and_parse_package_json(cwd: &Path) -> Result<(PathBuf, Package)> {
     let package_json_path = find_up("package.json", cwd)
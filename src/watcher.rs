@@ -0,0 +1,908 @@
+//! File change detection for `kn watch`.
+//!
+//! On Linux changes arrive through inotify, with one watch per directory.
+//! Elsewhere, or with `--poll` for network filesystems where inotify misses
+//! changes made by other machines, the tree is walked every 500 ms and
+//! modification times are compared. Either way events are coalesced: a batch
+//! is reported once no new event arrived for the debounce interval.
+//!
+//! Directories and files ignored by `.gitignore` or `.ignore` files are not
+//! watched, nor are `.git` and `node_modules`.
+
+use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::glob::{relative_path, GlobSet};
+use crate::ignore::IgnoreFiles;
+
+/// How often the polling backend walks the tree
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+/// Default quiet period before a batch of changes is reported
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// Directories never watched
+const SKIPPED_DIRS: [&str; 2] = [".git", "node_modules"];
+
+/// Replaced by the changed file in a script argument, running the script
+/// once per file
+pub const FILE_PLACEHOLDER: &str = "{file}";
+/// Replaced by all changed files in a script argument
+pub const FILES_PLACEHOLDER: &str = "{files}";
+
+/// Settings of the `kn.watch` section of package.json:
+///
+/// ```json
+/// "kn": {
+///   "watch": {
+///     "src/**/*.ts": "build",
+///     "*.graphql": "codegen",
+///     "tests/**": "test {files}",
+///     "ignore": ["src/generated"]
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct WatchConfig {
+    /// Patterns of files whose changes are ignored
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    /// Patterns mapped to the script, with arguments, their changes run
+    #[serde(flatten)]
+    pub scripts: IndexMap<String, String>,
+}
+
+impl WatchConfig {
+    pub fn rules(&self) -> Vec<WatchRule> {
+        self.scripts
+            .iter()
+            .map(|(pattern, command)| WatchRule::new(vec![pattern.clone()], command))
+            .collect()
+    }
+}
+
+/// A script run when files matching its patterns change
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchRule {
+    pub patterns: Vec<String>,
+    pub script: String,
+    /// Arguments, possibly with placeholders for the changed files
+    pub args: Vec<String>,
+}
+
+impl WatchRule {
+    /// `command` is the script name followed by its arguments
+    pub fn new(patterns: Vec<String>, command: &str) -> Self {
+        let mut words = command.split_whitespace().map(String::from);
+        WatchRule {
+            patterns,
+            script: words.next().unwrap_or_default(),
+            args: words.collect(),
+        }
+    }
+
+    /// Parse `<glob>=<script> [args...]`, `None` when a part is missing
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (pattern, command) = spec.split_once('=')?;
+        let rule = WatchRule::new(vec![pattern.trim().to_string()], command);
+        (!pattern.trim().is_empty() && !rule.script.is_empty()).then_some(rule)
+    }
+
+    /// The script followed by its arguments as written
+    pub fn command(&self) -> String {
+        std::iter::once(&self.script)
+            .chain(&self.args)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Whether the script runs once for each changed file
+    pub fn per_file(&self) -> bool {
+        self.args.iter().any(|arg| arg.contains(FILE_PLACEHOLDER))
+    }
+
+    /// The arguments of a run for the changed `files`, or for `file` when
+    /// running per file. `{files}` on its own expands to one argument per
+    /// file; an argument whose placeholder has nothing to fill in is dropped.
+    pub fn args_for(&self, file: Option<&str>, files: &[String]) -> Vec<String> {
+        let mut args = Vec::new();
+        for arg in &self.args {
+            if arg == FILES_PLACEHOLDER {
+                args.extend(files.iter().cloned());
+            } else if arg.contains(FILES_PLACEHOLDER) && !files.is_empty() {
+                args.push(arg.replace(FILES_PLACEHOLDER, &files.join(" ")));
+            } else if arg.contains(FILE_PLACEHOLDER) {
+                if let Some(file) = file {
+                    args.push(arg.replace(FILE_PLACEHOLDER, file));
+                }
+            } else if !arg.contains(FILES_PLACEHOLDER) {
+                args.push(arg.clone());
+            }
+        }
+        args
+    }
+}
+
+/// The watch settings of the package.json at `manifest`
+pub fn watch_config(manifest: &Path) -> Result<WatchConfig> {
+    let contents = fs::read_to_string(manifest)
+        .with_context(|| format!("Failed to read {}", manifest.display()))?;
+    let json: Value = serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse {}", manifest.display()))?;
+
+    match json["kn"].get("watch") {
+        Some(config) => serde_json::from_value(config.clone())
+            .with_context(|| format!("Invalid kn.watch in {}", manifest.display())),
+        None => Ok(WatchConfig::default()),
+    }
+}
+
+/// A change to a watched file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Created(PathBuf),
+    Modified(PathBuf),
+    Removed(PathBuf),
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+impl Change {
+    /// The file's path after the change, or the removed path
+    pub fn path(&self) -> &Path {
+        match self {
+            Change::Created(path) | Change::Modified(path) | Change::Removed(path) => path,
+            Change::Renamed { to, .. } => to,
+        }
+    }
+
+    /// The file's path before the change
+    fn previous_path(&self) -> &Path {
+        match self {
+            Change::Renamed { from, .. } => from,
+            change => change.path(),
+        }
+    }
+}
+
+/// Watches the files below a root that match a glob set
+pub struct Watcher {
+    filter: Filter,
+    debounce: Duration,
+    backend: Backend,
+    /// Changes not reported yet, at most one per file
+    pending: Vec<Change>,
+    last_event: Option<Instant>,
+}
+
+enum Backend {
+    #[cfg(target_os = "linux")]
+    Inotify(inotify::Inotify),
+    Poll {
+        files: BTreeMap<PathBuf, Stamp>,
+        last_scan: Instant,
+    },
+}
+
+/// What tells the polling backend a file changed
+type Stamp = (SystemTime, u64);
+
+/// Which paths below the root are watched
+struct Filter {
+    root: PathBuf,
+    globs: GlobSet,
+    ignores: IgnoreFiles,
+}
+
+impl Watcher {
+    /// Watch with inotify where available unless `poll` is set. Falls back
+    /// to polling when inotify cannot be set up; the error is returned
+    /// alongside so the caller can mention it.
+    pub fn new(
+        root: PathBuf,
+        globs: GlobSet,
+        poll: bool,
+        debounce: Duration,
+    ) -> (Self, Option<std::io::Error>) {
+        let mut filter = Filter {
+            ignores: IgnoreFiles::above(&root),
+            root,
+            globs,
+        };
+        let mut fallback = None;
+        let backend = if poll {
+            Backend::polling(&mut filter)
+        } else {
+            Backend::native(&mut filter).unwrap_or_else(|e| {
+                fallback = Some(e);
+                Backend::polling(&mut filter)
+            })
+        };
+
+        let watcher = Watcher {
+            filter,
+            debounce,
+            backend,
+            pending: Vec::new(),
+            last_event: None,
+        };
+        (watcher, fallback)
+    }
+
+    pub fn is_polling(&self) -> bool {
+        matches!(self.backend, Backend::Poll { .. })
+    }
+
+    /// Changes since the last batch, once the debounce interval passed
+    /// without new events; empty otherwise. Never blocks.
+    pub fn changes(&mut self) -> Vec<Change> {
+        let changes = match &mut self.backend {
+            #[cfg(target_os = "linux")]
+            Backend::Inotify(inotify) => inotify.read(&mut self.filter),
+            Backend::Poll { files, last_scan } => {
+                if last_scan.elapsed() < POLL_INTERVAL {
+                    Vec::new()
+                } else {
+                    *last_scan = Instant::now();
+                    scan_changes(&mut self.filter, files)
+                }
+            }
+        };
+
+        if !changes.is_empty() {
+            self.last_event = Some(Instant::now());
+            for change in changes {
+                merge(&mut self.pending, change);
+            }
+        }
+
+        match self.last_event {
+            Some(last) if last.elapsed() >= self.debounce => {
+                self.last_event = None;
+                std::mem::take(&mut self.pending)
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Fold `change` into the pending change of the same file, so a file that
+/// was created and then written is reported as created and one created and
+/// removed again not at all
+fn merge(pending: &mut Vec<Change>, change: Change) {
+    use Change::*;
+
+    let previous = pending
+        .iter()
+        .position(|c| c.path() == change.previous_path())
+        .map(|i| pending.remove(i));
+    let merged = match (previous, change) {
+        (Some(Created(_)), Modified(path)) => Some(Created(path)),
+        (Some(Created(_)), Removed(_)) => None,
+        (Some(Created(_)), Renamed { to, .. }) => Some(Created(to)),
+        (Some(Removed(_)), Created(path)) => Some(Modified(path)),
+        (Some(Renamed { from, .. }), Modified(to)) => Some(Renamed { from, to }),
+        (Some(Renamed { from, .. }), Removed(_)) => Some(Removed(from)),
+        (Some(Renamed { from, .. }), Renamed { to, .. }) if from == to => Some(Modified(to)),
+        (Some(Renamed { from, .. }), Renamed { to, .. }) => Some(Renamed { from, to }),
+        (_, change) => Some(change),
+    };
+    pending.extend(merged);
+}
+
+impl Backend {
+    fn polling(filter: &mut Filter) -> Self {
+        // The first scan only records what is already there
+        let mut files = BTreeMap::new();
+        scan_changes(filter, &mut files);
+        Backend::Poll {
+            files,
+            last_scan: Instant::now(),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn native(filter: &mut Filter) -> std::io::Result<Self> {
+        inotify::Inotify::new(filter).map(Backend::Inotify)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn native(_filter: &mut Filter) -> std::io::Result<Self> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "no native file watching on this platform",
+        ))
+    }
+}
+
+/// Walk the tree, update `files` and return what changed since the last
+/// walk. A file that disappeared while one with the same modification time
+/// and size appeared was renamed.
+fn scan_changes(filter: &mut Filter, files: &mut BTreeMap<PathBuf, Stamp>) -> Vec<Change> {
+    let mut current = BTreeMap::new();
+    let root = filter.root.clone();
+    filter.walk(&root, &mut |path, is_dir| {
+        if is_dir {
+            return;
+        }
+        if let Ok(metadata) = fs::metadata(path) {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            current.insert(path.to_path_buf(), (modified, metadata.len()));
+        }
+    });
+
+    let mut changes = Vec::new();
+    let mut removed: Vec<(&PathBuf, &Stamp)> = files
+        .iter()
+        .filter(|(path, _)| !current.contains_key(*path))
+        .collect();
+    for (path, stamp) in &current {
+        match files.get(path) {
+            Some(previous) if previous == stamp => {}
+            Some(_) => changes.push(Change::Modified(path.clone())),
+            None => match removed.iter().position(|(_, s)| *s == stamp) {
+                Some(i) => changes.push(Change::Renamed {
+                    from: removed.remove(i).0.clone(),
+                    to: path.clone(),
+                }),
+                None => changes.push(Change::Created(path.clone())),
+            },
+        }
+    }
+    changes.extend(
+        removed
+            .into_iter()
+            .map(|(path, _)| Change::Removed(path.clone())),
+    );
+
+    *files = current;
+    changes
+}
+
+impl Filter {
+    fn is_watched_dir(&self, path: &Path) -> bool {
+        let Some(relative) = relative_path(&self.root, path) else {
+            return false;
+        };
+        let name = relative.rsplit('/').next().unwrap_or(&relative);
+        !SKIPPED_DIRS.contains(&name)
+            && !self.globs.is_excluded(&relative)
+            && !self.ignores.is_ignored(path, true)
+    }
+
+    /// Whether `path` is watched, whether or not it still exists
+    fn is_watched_file(&self, path: &Path) -> bool {
+        let Some(relative) = relative_path(&self.root, path) else {
+            return false;
+        };
+        let name = relative.rsplit('/').next().unwrap_or(&relative);
+        !is_temporary(name)
+            && self.globs.is_match(&relative)
+            && !self.ignores.is_ignored(path, false)
+    }
+
+    /// Visit `dir` and the watched directories below it, and the watched
+    /// files in them, reading ignore files on the way
+    fn walk(&mut self, dir: &Path, visit: &mut dyn FnMut(&Path, bool)) {
+        self.ignores.load(dir);
+        visit(dir, true);
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.file_type() {
+                Ok(t) if t.is_dir() && self.is_watched_dir(&path) => self.walk(&path, visit),
+                Ok(t) if t.is_dir() => {}
+                Ok(_) if self.is_watched_file(&path) => visit(&path, false),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Files editors write while saving
+fn is_temporary(name: &str) -> bool {
+    name.ends_with('~')
+        || name.ends_with(".swp")
+        || name.ends_with(".swx")
+        || name.starts_with(".#")
+        || name == "4913"
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use rustix::fd::OwnedFd;
+    use rustix::fs::inotify::{self, CreateFlags, ReadFlags, WatchFlags};
+    use rustix::io::Errno;
+    use std::collections::{BTreeSet, HashMap};
+    use std::ffi::OsStr;
+    use std::io;
+    use std::mem::MaybeUninit;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use tracing::debug;
+
+    use super::{Change, Filter};
+    use crate::ignore::IGNORE_FILES;
+
+    /// One inotify watch per watched directory
+    pub struct Inotify {
+        fd: OwnedFd,
+        /// Directory of each watch descriptor
+        dirs: HashMap<i32, PathBuf>,
+        /// Watched files, to tell creations from writes and to know what
+        /// went away with a directory
+        files: BTreeSet<PathBuf>,
+        /// Paths moved away, by cookie, until the matching move into a
+        /// watched directory shows up; the pair can straddle two reads
+        moved_from: HashMap<u32, (PathBuf, bool)>,
+    }
+
+    /// An event read from the inotify descriptor
+    struct Event {
+        wd: i32,
+        flags: ReadFlags,
+        cookie: u32,
+        name: Option<PathBuf>,
+    }
+
+    fn watch_flags() -> WatchFlags {
+        WatchFlags::CLOSE_WRITE
+            | WatchFlags::MODIFY
+            | WatchFlags::CREATE
+            | WatchFlags::DELETE
+            | WatchFlags::MOVED_FROM
+            | WatchFlags::MOVED_TO
+            | WatchFlags::ONLYDIR
+            | WatchFlags::DONT_FOLLOW
+    }
+
+    impl Inotify {
+        pub fn new(filter: &mut Filter) -> io::Result<Self> {
+            let fd = inotify::init(CreateFlags::NONBLOCK | CreateFlags::CLOEXEC)?;
+            let mut inotify = Inotify {
+                fd,
+                dirs: HashMap::new(),
+                files: BTreeSet::new(),
+                moved_from: HashMap::new(),
+            };
+            let root = filter.root.clone();
+            inotify.add_tree(filter, &root, &mut Vec::new())?;
+            Ok(inotify)
+        }
+
+        /// Watch `dir` and the directories below it; watched files not seen
+        /// before are reported as created. Running out of watches is an
+        /// error; other directories that cannot be watched are skipped.
+        fn add_tree(
+            &mut self,
+            filter: &mut Filter,
+            dir: &Path,
+            changes: &mut Vec<Change>,
+        ) -> io::Result<()> {
+            let mut result = Ok(());
+            filter.walk(dir, &mut |path, is_dir| {
+                if !is_dir {
+                    if self.files.insert(path.to_path_buf()) {
+                        changes.push(Change::Created(path.to_path_buf()));
+                    }
+                    return;
+                }
+                if result.is_err() {
+                    return;
+                }
+                match inotify::add_watch(&self.fd, path, watch_flags()) {
+                    Ok(wd) => {
+                        self.dirs.insert(wd, path.to_path_buf());
+                    }
+                    Err(Errno::NOSPC) | Err(Errno::NOMEM) => {
+                        result = Err(io::Error::new(
+                            io::ErrorKind::OutOfMemory,
+                            "inotify watch limit reached (fs.inotify.max_user_watches)",
+                        ));
+                    }
+                    Err(e) => debug!("Cannot watch {}: {}", path.display(), e),
+                }
+            });
+            result
+        }
+
+        /// Forget the files below `dir`, reporting them as removed, and stop
+        /// watching it
+        fn remove_tree(&mut self, dir: &Path, changes: &mut Vec<Change>) {
+            let gone: Vec<PathBuf> = self
+                .files
+                .range(dir.to_path_buf()..)
+                .take_while(|path| path.starts_with(dir))
+                .cloned()
+                .collect();
+            for path in gone {
+                self.files.remove(&path);
+                changes.push(Change::Removed(path));
+            }
+            let fd = &self.fd;
+            self.dirs.retain(|wd, path| {
+                if path.starts_with(dir) {
+                    let _ = inotify::remove_watch(fd, *wd);
+                    return false;
+                }
+                true
+            });
+        }
+
+        /// Move the files below `from` to `to`, reporting them as renamed
+        fn rename_tree(
+            &mut self,
+            filter: &Filter,
+            from: &Path,
+            to: &Path,
+            changes: &mut Vec<Change>,
+        ) {
+            let moved: Vec<PathBuf> = self
+                .files
+                .range(from.to_path_buf()..)
+                .take_while(|path| path.starts_with(from))
+                .cloned()
+                .collect();
+            for path in moved {
+                self.files.remove(&path);
+                let Ok(rest) = path.strip_prefix(from) else {
+                    continue;
+                };
+                let new_path = to.join(rest);
+                if filter.is_watched_file(&new_path) {
+                    self.files.insert(new_path.clone());
+                    changes.push(Change::Renamed {
+                        from: path,
+                        to: new_path,
+                    });
+                } else {
+                    changes.push(Change::Removed(path));
+                }
+            }
+        }
+
+        fn read_events(&self) -> Vec<Event> {
+            let mut events = Vec::new();
+            let mut buffer = [MaybeUninit::uninit(); 8192];
+            let mut reader = inotify::Reader::new(&self.fd, &mut buffer);
+            loop {
+                match reader.next() {
+                    Ok(event) => events.push(Event {
+                        wd: event.wd(),
+                        flags: event.events(),
+                        cookie: event.cookie(),
+                        name: event
+                            .file_name()
+                            .map(|name| PathBuf::from(OsStr::from_bytes(name.to_bytes()))),
+                    }),
+                    Err(Errno::INTR) => continue,
+                    Err(_) => break,
+                }
+            }
+            events
+        }
+
+        /// Changes to watched files since the last call
+        pub fn read(&mut self, filter: &mut Filter) -> Vec<Change> {
+            let mut changes = Vec::new();
+            let mut new_dirs = Vec::new();
+            let mut moved_from = std::mem::take(&mut self.moved_from);
+            let previous: Vec<u32> = moved_from.keys().copied().collect();
+            let mut overflowed = false;
+
+            for event in self.read_events() {
+                let flags = event.flags;
+                if flags.contains(ReadFlags::QUEUE_OVERFLOW) {
+                    overflowed = true;
+                    continue;
+                }
+                if flags.contains(ReadFlags::IGNORED) {
+                    self.dirs.remove(&event.wd);
+                    continue;
+                }
+                let (Some(dir), Some(name)) = (self.dirs.get(&event.wd), &event.name) else {
+                    continue;
+                };
+                let dir = dir.clone();
+                let path = dir.join(name);
+                let is_dir = flags.contains(ReadFlags::ISDIR);
+
+                if !is_dir
+                    && IGNORE_FILES
+                        .iter()
+                        .any(|f| name.as_os_str() == OsStr::new(f))
+                {
+                    // Applies to what is watched from now on
+                    filter.ignores.load(&dir);
+                }
+
+                if flags.contains(ReadFlags::MOVED_FROM) {
+                    moved_from.insert(event.cookie, (path, is_dir));
+                } else if flags.contains(ReadFlags::MOVED_TO) {
+                    match moved_from.remove(&event.cookie) {
+                        Some((from, true)) => self.rename_tree(filter, &from, &path, &mut changes),
+                        Some((from, false)) if self.files.remove(&from) => {
+                            if !is_dir && filter.is_watched_file(&path) {
+                                self.files.insert(path.clone());
+                                changes.push(Change::Renamed {
+                                    from,
+                                    to: path.clone(),
+                                });
+                            } else {
+                                changes.push(Change::Removed(from));
+                            }
+                        }
+                        _ => {}
+                    }
+                    if is_dir {
+                        if filter.is_watched_dir(&path) {
+                            new_dirs.push(path);
+                        }
+                    } else if !self.files.contains(&path) && filter.is_watched_file(&path) {
+                        self.files.insert(path.clone());
+                        changes.push(Change::Created(path));
+                    }
+                } else if flags.contains(ReadFlags::DELETE) {
+                    if is_dir {
+                        self.remove_tree(&path, &mut changes);
+                    } else if self.files.remove(&path) {
+                        changes.push(Change::Removed(path));
+                    }
+                } else if is_dir {
+                    if filter.is_watched_dir(&path) {
+                        new_dirs.push(path);
+                    }
+                } else if self.files.contains(&path) {
+                    if !flags.contains(ReadFlags::CREATE) {
+                        changes.push(Change::Modified(path));
+                    }
+                } else if filter.is_watched_file(&path) && path.is_file() {
+                    self.files.insert(path.clone());
+                    changes.push(Change::Created(path));
+                }
+            }
+
+            // Moved out of the watched tree when still unmatched a read
+            // later; after an overflow the rescan settles everything
+            let moved_out: Vec<(PathBuf, bool)> = if overflowed {
+                moved_from.into_values().collect()
+            } else {
+                let moved_out = previous
+                    .iter()
+                    .filter_map(|cookie| moved_from.remove(cookie))
+                    .collect();
+                self.moved_from = moved_from;
+                moved_out
+            };
+            for (path, is_dir) in moved_out {
+                if is_dir {
+                    self.remove_tree(&path, &mut changes);
+                } else if self.files.remove(&path) {
+                    changes.push(Change::Removed(path));
+                }
+            }
+
+            if overflowed {
+                // Events were lost: rewatch everything and compare the files
+                // found with the ones known, treating every file as changed
+                debug!("inotify queue overflowed, rescanning");
+                let known = std::mem::take(&mut self.files);
+                let root = filter.root.clone();
+                let mut found = Vec::new();
+                let _ = self.add_tree(filter, &root, &mut found);
+                changes.extend(found.into_iter().map(|change| match change {
+                    Change::Created(path) if known.contains(&path) => Change::Modified(path),
+                    change => change,
+                }));
+                changes.extend(
+                    known
+                        .into_iter()
+                        .filter(|path| !self.files.contains(path))
+                        .map(Change::Removed),
+                );
+                return changes;
+            }
+
+            for dir in new_dirs {
+                // Files may have appeared before the directory was watched
+                let _ = self.add_tree(filter, &dir, &mut changes);
+            }
+            changes
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cleanup_test_dir(dir: &Path) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).expect("Failed to cleanup test directory");
+        }
+    }
+
+    /// Wait for the watcher to report a batch
+    fn next_batch(watcher: &mut Watcher) -> Vec<Change> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            let changes = watcher.changes();
+            if !changes.is_empty() {
+                return changes;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        Vec::new()
+    }
+
+    fn check_backend(test_dir: &Path, poll: bool) {
+        cleanup_test_dir(test_dir);
+        fs::create_dir_all(test_dir.join("src")).unwrap();
+        fs::create_dir_all(test_dir.join("node_modules/pkg")).unwrap();
+        fs::write(test_dir.join("src/a.ts"), "a").unwrap();
+        fs::write(test_dir.join(".gitignore"), "*.gen.ts\ntmp/\n").unwrap();
+
+        let mut globs = GlobSet::new(&["**/*.ts"]);
+        globs.ignore(&["src/generated"]);
+        let (mut watcher, fallback) =
+            Watcher::new(test_dir.to_path_buf(), globs, poll, DEFAULT_DEBOUNCE);
+        assert!(fallback.is_none());
+        assert_eq!(watcher.is_polling(), poll || cfg!(not(target_os = "linux")));
+        assert!(watcher.changes().is_empty());
+
+        // Ignored, unmatched and skipped files are not reported; a burst of
+        // writes to one file is a single change
+        std::thread::sleep(Duration::from_millis(20));
+        fs::create_dir_all(test_dir.join("src/generated")).unwrap();
+        fs::write(test_dir.join("src/generated/api.ts"), "x").unwrap();
+        fs::write(test_dir.join("src/notes.md"), "x").unwrap();
+        fs::write(test_dir.join("src/schema.gen.ts"), "x").unwrap();
+        fs::create_dir_all(test_dir.join("tmp")).unwrap();
+        fs::write(test_dir.join("tmp/t.ts"), "x").unwrap();
+        fs::write(test_dir.join("node_modules/pkg/index.ts"), "x").unwrap();
+        for content in ["b", "bb", "bbb"] {
+            fs::write(test_dir.join("src/a.ts"), content).unwrap();
+        }
+        assert_eq!(
+            next_batch(&mut watcher),
+            vec![Change::Modified(test_dir.join("src/a.ts"))]
+        );
+
+        // Files in new directories are picked up
+        fs::create_dir_all(test_dir.join("src/lib/deep")).unwrap();
+        fs::write(test_dir.join("src/lib/deep/b.ts"), "b").unwrap();
+        assert_eq!(
+            next_batch(&mut watcher),
+            vec![Change::Created(test_dir.join("src/lib/deep/b.ts"))]
+        );
+
+        // Renames and removals
+        fs::rename(test_dir.join("src/a.ts"), test_dir.join("src/c.ts")).unwrap();
+        assert_eq!(
+            next_batch(&mut watcher),
+            vec![Change::Renamed {
+                from: test_dir.join("src/a.ts"),
+                to: test_dir.join("src/c.ts"),
+            }]
+        );
+        let outside = test_dir.with_extension("ts");
+        fs::rename(test_dir.join("src/c.ts"), &outside).unwrap();
+        assert_eq!(
+            next_batch(&mut watcher),
+            vec![Change::Removed(test_dir.join("src/c.ts"))]
+        );
+        fs::remove_file(outside).unwrap();
+        fs::remove_dir_all(test_dir.join("src/lib")).unwrap();
+        assert_eq!(
+            next_batch(&mut watcher),
+            vec![Change::Removed(test_dir.join("src/lib/deep/b.ts"))]
+        );
+
+        cleanup_test_dir(test_dir);
+    }
+
+    #[test]
+    fn test_native_watcher() {
+        check_backend(Path::new("/tmp/kn_test_watcher_native"), false);
+    }
+
+    #[test]
+    fn test_polling_watcher() {
+        check_backend(Path::new("/tmp/kn_test_watcher_poll"), true);
+    }
+
+    #[test]
+    fn test_watch_rules() {
+        let test_dir = Path::new("/tmp/kn_test_watch_config");
+        cleanup_test_dir(test_dir);
+        fs::create_dir_all(test_dir).unwrap();
+        let manifest = test_dir.join("package.json");
+        fs::write(
+            &manifest,
+            r#"{"kn": {"watch": {"src/**/*.ts": "build", "ignore": ["dist"], "*.graphql": "codegen --watch=false"}}}"#,
+        )
+        .unwrap();
+
+        let config = watch_config(&manifest).unwrap();
+        assert_eq!(config.ignore, vec!["dist"]);
+        let rules = config.rules();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].script, "build");
+        assert_eq!(rules[1].patterns, vec!["*.graphql"]);
+        assert_eq!(rules[1].args, vec!["--watch=false"]);
+
+        let rule = WatchRule::parse("tests/**=test --bail {files}").unwrap();
+        assert_eq!(rule.patterns, vec!["tests/**"]);
+        assert!(!rule.per_file());
+        let files = vec!["tests/a.js".to_string(), "tests/b.js".to_string()];
+        assert_eq!(
+            rule.args_for(None, &files),
+            vec!["--bail", "tests/a.js", "tests/b.js"]
+        );
+        assert_eq!(rule.args_for(None, &[]), vec!["--bail"]);
+
+        let rule = WatchRule::parse("*.css=lint --file={file}").unwrap();
+        assert!(rule.per_file());
+        assert_eq!(rule.args_for(Some("a.css"), &files), vec!["--file=a.css"]);
+        assert!(rule.args_for(None, &[]).is_empty());
+
+        assert!(WatchRule::parse("src/**").is_none());
+        assert!(WatchRule::parse("=build").is_none());
+        assert!(WatchRule::parse("src/**= ").is_none());
+
+        fs::write(&manifest, r#"{"kn": {"watch": {"ignore": "dist"}}}"#).unwrap();
+        assert!(watch_config(&manifest).is_err());
+
+        cleanup_test_dir(test_dir);
+    }
+
+    #[test]
+    fn test_merge_changes() {
+        let path = |name: &str| PathBuf::from(name);
+        let merged = |changes: Vec<Change>| {
+            let mut pending = Vec::new();
+            for change in changes {
+                merge(&mut pending, change);
+            }
+            pending
+        };
+
+        assert_eq!(
+            merged(vec![
+                Change::Created(path("a")),
+                Change::Modified(path("a"))
+            ]),
+            vec![Change::Created(path("a"))]
+        );
+        assert!(merged(vec![Change::Created(path("a")), Change::Removed(path("a"))]).is_empty());
+        assert_eq!(
+            merged(vec![Change::Removed(path("a")), Change::Created(path("a"))]),
+            vec![Change::Modified(path("a"))]
+        );
+        assert_eq!(
+            merged(vec![
+                Change::Renamed {
+                    from: path("a"),
+                    to: path("b")
+                },
+                Change::Modified(path("b")),
+                Change::Renamed {
+                    from: path("b"),
+                    to: path("c")
+                },
+            ]),
+            vec![Change::Renamed {
+                from: path("a"),
+                to: path("c")
+            }]
+        );
+    }
+}
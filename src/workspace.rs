@@ -0,0 +1,443 @@
+//! Monorepo workspace discovery.
+//!
+//! Workspace packages come from the `workspaces` field of the root
+//! package.json (npm, yarn, bun) or from `pnpm-workspace.yaml`. Dependencies
+//! between packages are read from `dependencies` and `devDependencies`.
+
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A package of the workspace
+#[derive(Debug, Clone)]
+pub struct WorkspacePackage {
+    pub name: String,
+    pub dir: PathBuf,
+    pub scripts: IndexMap<String, String>,
+    /// Names of other workspace packages this one depends on
+    pub dependencies: Vec<String>,
+}
+
+impl WorkspacePackage {
+    pub fn manifest_path(&self) -> PathBuf {
+        self.dir.join("package.json")
+    }
+}
+
+/// Nearest ancestor of `cwd` that declares workspaces
+pub fn find_root(cwd: &Path) -> Option<PathBuf> {
+    cwd.ancestors()
+        .find(|dir| !workspace_patterns(dir).is_empty())
+        .map(Path::to_path_buf)
+}
+
+/// Workspace globs declared in `root`, `!`-prefixed ones excluding matches
+pub fn workspace_patterns(root: &Path) -> Vec<String> {
+    if let Ok(contents) = fs::read_to_string(root.join("pnpm-workspace.yaml")) {
+        return parse_pnpm_workspace(&contents);
+    }
+
+    let Ok(contents) = fs::read_to_string(root.join("package.json")) else {
+        return Vec::new();
+    };
+    let Ok(json) = serde_json::from_str::<Value>(&contents) else {
+        return Vec::new();
+    };
+
+    // Either `["packages/*"]` or yarn's `{ "packages": ["packages/*"] }`
+    let patterns = match &json["workspaces"] {
+        Value::Array(items) => items,
+        Value::Object(map) => match map.get("packages") {
+            Some(Value::Array(items)) => items,
+            _ => return Vec::new(),
+        },
+        _ => return Vec::new(),
+    };
+    patterns
+        .iter()
+        .filter_map(|p| p.as_str().map(str::to_string))
+        .collect()
+}
+
+/// Read the `packages` list of a pnpm-workspace.yaml
+fn parse_pnpm_workspace(contents: &str) -> Vec<String> {
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with([' ', '\t', '-']) {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+        if in_packages {
+            if let Some(item) = trimmed.strip_prefix('-') {
+                let item = item.split(" #").next().unwrap_or(item).trim();
+                patterns.push(item.trim_matches(|c| c == '"' || c == '\'').to_string());
+            }
+        }
+    }
+    patterns
+}
+
+/// All packages of the workspace at `root`, sorted by directory
+pub fn discover(root: &Path) -> Result<Vec<WorkspacePackage>> {
+    let mut included = Vec::new();
+    let mut excluded = HashSet::new();
+    for pattern in workspace_patterns(root) {
+        match pattern.strip_prefix('!') {
+            Some(negated) => excluded.extend(match_dirs(root, negated)),
+            None => included.extend(match_dirs(root, &pattern)),
+        }
+    }
+    included.sort();
+    included.dedup();
+
+    let mut packages = Vec::new();
+    for dir in included.into_iter().filter(|d| !excluded.contains(d)) {
+        let manifest = dir.join("package.json");
+        let contents = fs::read_to_string(&manifest)
+            .with_context(|| format!("Failed to read {}", manifest.display()))?;
+        let json: Value = serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse {}", manifest.display()))?;
+
+        let name = match json["name"].as_str() {
+            Some(name) => name.to_string(),
+            None => dir
+                .strip_prefix(root)
+                .unwrap_or(&dir)
+                .to_string_lossy()
+                .to_string(),
+        };
+        let scripts = json["scripts"]
+            .as_object()
+            .map(|map| {
+                map.iter()
+                    .filter_map(|(k, v)| v.as_str().map(|cmd| (k.clone(), cmd.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let dependencies = ["dependencies", "devDependencies"]
+            .iter()
+            .filter_map(|key| json[key].as_object())
+            .flat_map(|deps| deps.keys().cloned())
+            .collect();
+
+        packages.push(WorkspacePackage {
+            name,
+            dir,
+            scripts,
+            dependencies,
+        });
+    }
+
+    let names: HashSet<String> = packages.iter().map(|p| p.name.clone()).collect();
+    for package in &mut packages {
+        package.dependencies.retain(|dep| names.contains(dep));
+        // A package may be in both dependencies and devDependencies
+        package.dependencies.sort();
+        package.dependencies.dedup();
+    }
+    Ok(packages)
+}
+
+/// Package directories under `root` matching a workspace glob
+fn match_dirs(root: &Path, pattern: &str) -> Vec<PathBuf> {
+    let segments: Vec<&str> = pattern
+        .trim_start_matches("./")
+        .trim_end_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty() && *s != ".")
+        .collect();
+    let mut found = Vec::new();
+    walk(root, &segments, &mut found);
+    found
+}
+
+fn walk(dir: &Path, segments: &[&str], found: &mut Vec<PathBuf>) {
+    let Some((segment, rest)) = segments.split_first() else {
+        if dir.join("package.json").is_file() {
+            found.push(dir.to_path_buf());
+        }
+        return;
+    };
+
+    if !segment.contains(['*', '?']) {
+        walk(&dir.join(segment), rest, found);
+        return;
+    }
+
+    if *segment == "**" {
+        walk(dir, rest, found);
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut subdirs: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|e| e.path())
+        .collect();
+    subdirs.sort();
+
+    for subdir in subdirs {
+        let name = subdir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        if name == "node_modules" || name.starts_with('.') {
+            continue;
+        }
+        if *segment == "**" {
+            walk(&subdir, segments, found);
+        } else if wildcard_match(segment, &name) {
+            walk(&subdir, rest, found);
+        }
+    }
+}
+
+/// Match `*` (any run of characters) and `?` (one character)
+pub fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether `package` is selected by a `--filter` value: a name glob
+/// (`@scope/*`) or a directory glob relative to the root (`./apps/*`).
+/// A leading `!` excludes instead.
+pub fn filter_matches(filter: &str, package: &WorkspacePackage, root: &Path) -> bool {
+    let filter = filter.strip_prefix('!').unwrap_or(filter);
+    if let Some(path) = filter.strip_prefix("./") {
+        let relative = package
+            .dir
+            .strip_prefix(root)
+            .unwrap_or(&package.dir)
+            .to_string_lossy()
+            .replace('\\', "/");
+        return wildcard_match(path.trim_end_matches('/'), &relative);
+    }
+    wildcard_match(filter, &package.name)
+}
+
+/// Apply `--filter` values: positive filters select, `!` filters exclude
+pub fn apply_filters(
+    packages: Vec<WorkspacePackage>,
+    filters: &[String],
+    root: &Path,
+) -> Vec<WorkspacePackage> {
+    let (exclude, include): (Vec<&String>, Vec<&String>) =
+        filters.iter().partition(|f| f.starts_with('!'));
+    packages
+        .into_iter()
+        .filter(|p| include.is_empty() || include.iter().any(|f| filter_matches(f, p, root)))
+        .filter(|p| !exclude.iter().any(|f| filter_matches(f, p, root)))
+        .collect()
+}
+
+/// Files changed since `git_ref`, including
+/// uncommitted and untracked changes
+pub fn changed_since(root: &Path, git_ref: &str) -> Result<Vec<PathBuf>> {
+    let git = |args: &[&str]| -> Result<String> {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(root)
+            .output()
+            .context("Failed to run git")?;
+        if !output.status.success() {
+            bail!(
+                "git {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    };
+
+    let toplevel = PathBuf::from(git(&["rev-parse", "--show-toplevel"])?.trim());
+    let mut files = git(&["diff", "--name-only", git_ref, "--"])?;
+    files.push_str(&git(&[
+        "ls-files",
+        "--others",
+        "--exclude-standard",
+        "--full-name",
+    ])?);
+
+    Ok(files
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| toplevel.join(line))
+        .collect())
+}
+
+/// Keep packages that contain at least one of `changed_files`
+pub fn filter_changed(
+    packages: Vec<WorkspacePackage>,
+    changed_files: &[PathBuf],
+) -> Vec<WorkspacePackage> {
+    packages
+        .into_iter()
+        .filter(|p| changed_files.iter().any(|f| f.starts_with(&p.dir)))
+        .collect()
+}
+
+/// Order `selected` so every package comes after the selected packages it
+/// depends on (directly or through unselected packages). Returns the
+/// packages with, for each, the indices of its selected dependencies.
+pub fn topological_order(
+    all: &[WorkspacePackage],
+    selected: Vec<WorkspacePackage>,
+) -> Result<Vec<(WorkspacePackage, Vec<usize>)>> {
+    let selected_names: HashSet<String> = selected.iter().map(|p| p.name.clone()).collect();
+    let by_name: IndexMap<&str, &WorkspacePackage> =
+        all.iter().map(|p| (p.name.as_str(), p)).collect();
+
+    // Nearest selected packages reachable through the dependency graph
+    let selected_deps = |package: &WorkspacePackage| -> Vec<String> {
+        let mut deps = Vec::new();
+        let mut seen = HashSet::new();
+        let mut stack: Vec<&str> = package.dependencies.iter().map(String::as_str).collect();
+        while let Some(name) = stack.pop() {
+            if !seen.insert(name) {
+                continue;
+            }
+            if selected_names.contains(name) {
+                deps.push(name.to_string());
+            } else if let Some(dep) = by_name.get(name) {
+                stack.extend(dep.dependencies.iter().map(String::as_str));
+            }
+        }
+        deps
+    };
+
+    let mut remaining: Vec<(WorkspacePackage, Vec<String>)> = selected
+        .into_iter()
+        .map(|p| {
+            let deps = selected_deps(&p);
+            (p, deps)
+        })
+        .collect();
+    let mut ordered: Vec<(WorkspacePackage, Vec<usize>)> = Vec::new();
+
+    while !remaining.is_empty() {
+        let position = remaining.iter().position(|(_, deps)| {
+            deps.iter()
+                .all(|dep| ordered.iter().any(|(p, _)| &p.name == dep))
+        });
+        let Some(position) = position else {
+            let names: Vec<&str> = remaining.iter().map(|(p, _)| p.name.as_str()).collect();
+            bail!(
+                "Dependency cycle between workspace packages: {}",
+                names.join(", ")
+            );
+        };
+
+        let (package, deps) = remaining.remove(position);
+        let indices = deps
+            .iter()
+            .filter_map(|dep| ordered.iter().position(|(p, _)| &p.name == dep))
+            .collect();
+        ordered.push((package, indices));
+    }
+
+    Ok(ordered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cleanup_test_dir(dir: &Path) {
+        if dir.exists() {
+            fs::remove_dir_all(dir).expect("Failed to cleanup test directory");
+        }
+    }
+
+    fn write_package(dir: &Path, json: &str) {
+        fs::create_dir_all(dir).expect("Failed to create package directory");
+        fs::write(dir.join("package.json"), json).expect("Failed to write package.json");
+    }
+
+    #[test]
+    fn test_discover_and_order() {
+        let root = Path::new("/tmp/kn_test_workspace");
+        cleanup_test_dir(root);
+        write_package(
+            root,
+            r#"{ "name": "root", "workspaces": ["packages/*", "apps/*", "!apps/legacy"] }"#,
+        );
+        write_package(
+            &root.join("apps/web"),
+            r#"{ "name": "web", "dependencies": { "ui": "*", "utils": "*", "react": "^18" }, "devDependencies": { "ui": "*" }, "scripts": { "build": "next build" } }"#,
+        );
+        write_package(&root.join("apps/legacy"), r#"{ "name": "legacy" }"#);
+        write_package(
+            &root.join("packages/ui"),
+            r#"{ "name": "ui", "devDependencies": { "utils": "*" }, "scripts": { "test": "vitest" } }"#,
+        );
+        write_package(
+            &root.join("packages/utils"),
+            r#"{ "name": "utils", "scripts": { "build": "tsc" } }"#,
+        );
+
+        assert_eq!(find_root(&root.join("apps/web")).as_deref(), Some(root));
+
+        let all = discover(root).expect("Failed to discover packages");
+        let names: Vec<&str> = all.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["web", "ui", "utils"]);
+        assert_eq!(all[0].dependencies, ["ui", "utils"]);
+
+        // `ui` has no build script, so `web` waits on `utils` through it
+        let with_build: Vec<WorkspacePackage> = all
+            .iter()
+            .filter(|p| p.scripts.contains_key("build"))
+            .cloned()
+            .collect();
+        let ordered = topological_order(&all, with_build).expect("Failed to order packages");
+        assert_eq!(ordered[0].0.name, "utils");
+        assert_eq!(ordered[1].0.name, "web");
+        assert_eq!(ordered[1].1, [0]);
+
+        let filtered = apply_filters(all.clone(), &["./packages/*".to_string()], root);
+        assert_eq!(filtered.len(), 2);
+        let filtered = apply_filters(all, &["!ui".to_string()], root);
+        assert_eq!(filtered.len(), 2);
+
+        cleanup_test_dir(root);
+    }
+
+    #[test]
+    fn test_parse_pnpm_workspace() {
+        let yaml = "packages:\n  - 'packages/*'\n  - \"apps/**\" # apps\n  - '!**/test/**'\ncatalog:\n  - react\n";
+        assert_eq!(
+            parse_pnpm_workspace(yaml),
+            ["packages/*", "apps/**", "!**/test/**"]
+        );
+        assert!(wildcard_match("@scope/*", "@scope/ui"));
+        assert!(!wildcard_match("@scope/*", "ui"));
+    }
+}